@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! An encoder for the Animated Portable Network Graphics format.
+
+use image::{Delay, RgbaImage};
+use png::{BitDepth, BlendOp, ColorType, DisposeOp, EncodingError};
+
+/// Encodes `frames` into an APNG image which loops forever.
+///
+/// All frames must have the same dimensions. The delay of each frame is
+/// rounded to milliseconds.
+pub fn encode(frames: &[(RgbaImage, Delay)]) -> Result<Vec<u8>, EncodingError> {
+    let (width, height) = frames.first().map_or((1, 1), |(f, _)| f.dimensions());
+    let mut buf = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buf, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_animated(u32::try_from(frames.len()).unwrap_or(u32::MAX), 0)?;
+    let mut writer = encoder.write_header()?;
+    for (image, delay) in frames {
+        let (numer, denom) = delay.numer_denom_ms();
+        let delay = u16::try_from(numer / denom.max(1)).unwrap_or(u16::MAX);
+        writer.set_frame_delay(delay, 1000)?;
+        // Each frame replaces the whole canvas:
+        writer.set_blend_op(BlendOp::Source)?;
+        writer.set_dispose_op(DisposeOp::None)?;
+        writer.write_image_data(image)?;
+    }
+    writer.finish()?;
+    Ok(buf)
+}
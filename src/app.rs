@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::{
+    fmt::Write,
     fs,
     io::{self, Read},
     time::Duration,
@@ -11,11 +12,16 @@ use std::{
 use anyhow::{Context, bail};
 use bat::PrettyPrinter;
 use clap::Parser;
-use image::ImageFormat;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
 use indicatif::ProgressBar;
 use serde_json::json;
 
-use crate::{cli::Opt, generate};
+use crate::{
+    cli::{IconSet, Opt, Target},
+    generate,
+    preprocess::{self, FrameSelection},
+    resample::{self, Resampler},
+};
 
 const HTML: &str = concat!(
     r#"<link rel="apple-touch-icon" sizes="180x180" href="/apple-touch-icon.png" />"#,
@@ -28,6 +34,16 @@ const HTML: &str = concat!(
     '\n'
 );
 
+const APPLE_PRECOMPOSED_HTML: &str = r#"<link rel="apple-touch-icon-precomposed" sizes="180x180" href="/apple-touch-icon-precomposed.png" />"#;
+
+const ANIMATED_HTML: &str =
+    r#"<link rel="icon" type="image/png" sizes="32x32" href="/favicon-animated.png" />"#;
+
+const MICROSOFT_HTML: &str = concat!(
+    r#"<meta name="msapplication-config" content="/browserconfig.xml" />"#,
+    '\n'
+);
+
 /// Runs the program and returns the result.
 #[allow(clippy::too_many_lines)]
 pub fn run() -> anyhow::Result<()> {
@@ -56,40 +72,210 @@ pub fn run() -> anyhow::Result<()> {
             .starts_with(b"#define")
             .then_some(crate::cli::Format::Xbm)
     });
+    #[cfg(feature = "psd")]
+    let format = format.or_else(|| {
+        input
+            .starts_with(b"8BPS")
+            .then_some(crate::cli::Format::Psd)
+    });
+    #[cfg(feature = "psd")]
+    let is_psd = matches!(format, Some(crate::cli::Format::Psd));
+    #[cfg(not(feature = "psd"))]
+    let is_psd = false;
+    if opt.layer.is_some() && !is_psd {
+        bail!("'--layer' can only be used with a PSD image");
+    }
+    if (opt.frame.is_some() || opt.apng) && is_psd {
+        bail!("'--frame' and '--apng' cannot be used with a PSD image");
+    }
     #[allow(clippy::option_if_let_else)]
-    let image = match format {
+    let (image, icc_profile, frames) = match format {
         #[cfg(feature = "xbm")]
         Some(crate::cli::Format::Xbm) => {
             let decoder = xbm::Decoder::new(std::io::Cursor::new(input))
                 .context("could not create new XBM decoder")?;
-            image::DynamicImage::from_decoder(decoder).map_err(anyhow::Error::from)
+            DynamicImage::from_decoder(decoder)
+                .map(|image| (image, None, None))
+                .map_err(anyhow::Error::from)
         }
+        #[cfg(feature = "psd")]
+        Some(crate::cli::Format::Psd) => crate::psd::Document::new(&input)
+            .map_err(anyhow::Error::from)
+            .and_then(|document| {
+                let image = match opt.layer {
+                    Some(ref layer) => document
+                        .layer(layer)?
+                        .with_context(|| format!("could not find layer {layer:?}"))?,
+                    None => document.composite()?,
+                };
+                Ok((image, document.icc_profile().map(<[u8]>::to_vec), None))
+            }),
         format => {
             let format = if let Some(f) = format {
                 f.try_into()
             } else {
-                image::guess_format(&input)
-                    .or_else(|err| opt.input.map_or_else(|| Err(err), ImageFormat::from_path))
+                image::guess_format(&input).or_else(|err| {
+                    opt.input
+                        .as_ref()
+                        .map_or_else(|| Err(err), ImageFormat::from_path)
+                })
             }
             .context("could not determine the image format")?;
-            image::load_from_memory_with_format(&input, format).map_err(anyhow::Error::from)
+            let frames = if opt.frame.is_some() || opt.apng {
+                preprocess::frames(&input, format).context("could not read the image")?
+            } else {
+                None
+            };
+            let frame = match (opt.frame, &frames) {
+                (Some(selection), Some(frames)) => {
+                    let index = preprocess::select_frame(frames, selection)
+                        .with_context(|| format!("image has only {} frames", frames.len()))?;
+                    Some(frames[index].buffer().clone())
+                }
+                (Some(FrameSelection::Index(index)), None) if index > 0 => {
+                    bail!("image has only 1 frame")
+                }
+                _ => None,
+            };
+            ImageReader::with_format(io::Cursor::new(&input), format)
+                .into_decoder()
+                .and_then(|mut decoder| {
+                    let orientation = decoder.orientation()?;
+                    let icc_profile = decoder.icc_profile()?;
+                    // The still image and the frames of the animated PNG
+                    // favicon are processed in the same way:
+                    let process = |mut image: DynamicImage| {
+                        if !opt.no_auto_orient {
+                            image.apply_orientation(orientation);
+                        }
+                        if matches!(format, ImageFormat::Hdr | ImageFormat::OpenExr) {
+                            image = preprocess::tone_map(&image, opt.tone_map.into(), opt.exposure);
+                        }
+                        image
+                    };
+                    let image = process(match frame {
+                        Some(frame) => frame.into(),
+                        None => DynamicImage::from_decoder(decoder)?,
+                    });
+                    let frames = frames.filter(|_| opt.apng).map(|frames| {
+                        frames
+                            .into_iter()
+                            .map(|frame| {
+                                let delay = frame.delay();
+                                (process(frame.into_buffer().into()), delay)
+                            })
+                            .collect::<Vec<_>>()
+                    });
+                    Ok((image, icc_profile, frames))
+                })
+                .map_err(anyhow::Error::from)
         }
     }
     .context("could not read the image")?;
+    let mut icc_profile = icc_profile;
+    // A malformed or unsupported ICC profile is ignored, and the image is used
+    // as is:
+    let mut to_srgb = |image| match icc_profile
+        .as_deref()
+        .map(|icc_profile| preprocess::to_srgb(&image, icc_profile))
+    {
+        Some(Ok(image)) => image,
+        Some(Err(err)) => {
+            eprintln!("Warning: could not convert the image to sRGB: {err}");
+            icc_profile = None;
+            image
+        }
+        None => image,
+    };
+    let image = to_srgb(image);
+    let image = match opt.trim {
+        Some(tolerance) => preprocess::trim(&image, tolerance)
+            .context("image does not have any non-transparent pixels")?,
+        None => image,
+    };
     if image.width() != image.height() {
         bail!("image is not square");
     }
-    let image = image.into_rgba8().into();
+    let frames = if opt.apng {
+        let frames = frames.context("image is not animated")?;
+        let frames = frames
+            .into_iter()
+            .map(|(image, delay)| (to_srgb(image), delay))
+            .collect::<Vec<_>>();
+        Some(frames)
+    } else {
+        None
+    };
 
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(50));
     pb.set_message("Generating favicons");
 
-    let filter = opt.filter.into();
-    let android_favicons = generate::for_android(&image, filter);
-    let apple_favicon = generate::for_apple(&image, filter);
-    let png_favicons = generate::png_favicons(&image, filter);
-    let ico_favicon = generate::ico_favicon(&image, filter, opt.png)?;
+    let resampler = Resampler::new(image, opt.sampling_filter(), opt.linear, opt.progressive);
+    let background = image::Rgba(opt.background_color.to_rgba8());
+    let style = |icon_set| generate::Style {
+        background: (!opt.no_flatten && opt.flatten.contains(&icon_set)).then_some(background),
+        padding: opt.padding(icon_set),
+        shape: match icon_set {
+            IconSet::Android | IconSet::Favicon => opt.shape,
+            IconSet::Apple | IconSet::Microsoft => generate::Shape::Square,
+        },
+        sharpen: opt.sharpen && icon_set == IconSet::Favicon,
+    };
+    let android_favicons = generate::for_android(&resampler, style(IconSet::Android));
+    let apple_favicon = generate::for_apple(&resampler, style(IconSet::Apple));
+    let apple_legacy_favicons = opt
+        .target
+        .contains(&Target::AppleLegacy)
+        .then(|| generate::for_apple_legacy(&resampler, style(IconSet::Apple)));
+    let png_favicons = generate::png_favicons(&resampler, style(IconSet::Favicon));
+    let png_threshold = if opt.png {
+        Some(u32::MIN)
+    } else {
+        opt.ico_png_threshold
+    };
+    let ico_favicon = generate::ico_favicon(
+        &resampler,
+        &opt.ico_sizes,
+        png_threshold,
+        opt.ico_compat,
+        opt.dither,
+        style(IconSet::Favicon),
+    )?;
+    let animated_favicon = frames
+        .map(|frames| {
+            generate::animated_favicon(
+                frames,
+                opt.sampling_filter(),
+                opt.linear,
+                opt.dither,
+                style(IconSet::Favicon),
+            )
+        })
+        .transpose()
+        .context("could not encode the animated PNG favicon")?;
+    let app_ico = opt
+        .target
+        .contains(&Target::AppIco)
+        .then(|| generate::app_ico(&resampler, opt.dither))
+        .transpose()?;
+    let microsoft_tiles = opt
+        .target
+        .contains(&Target::Microsoft)
+        .then(|| generate::for_microsoft(&resampler, style(IconSet::Microsoft)));
+    let mask_icon = opt
+        .target
+        .contains(&Target::MaskIcon)
+        .then(|| generate::mask_icon(&resampler));
+    let linux_icons = opt
+        .target
+        .contains(&Target::Hicolor)
+        .then(|| generate::for_linux(&resampler));
+    let is_apple_startup = opt.target.contains(&Target::AppleStartup);
+    let icns_icon = opt
+        .icns
+        .then(|| generate::icns_icon(&resampler, opt.dither))
+        .transpose()?;
     let webmanifest = json!({
         "name": opt.name,
         "short_name": opt.short_name.unwrap_or(opt.name),
@@ -124,23 +310,50 @@ pub fn run() -> anyhow::Result<()> {
             favicon.width(),
             favicon.height()
         ));
-        favicon
+        resample::quantize(&favicon, opt.dither)
             .save(&file)
             .with_context(|| format!("could not write the image to {}", file.display()))?;
     }
 
     let apple_file = out_dir.join("apple-touch-icon.png");
-    apple_favicon
+    resample::quantize(&apple_favicon, opt.dither)
         .save(&apple_file)
         .with_context(|| format!("could not write the image to {}", apple_file.display()))?;
 
+    let mut apple_legacy_links = Vec::new();
+    if let Some(apple_legacy_favicons) = apple_legacy_favicons {
+        for favicon in &apple_legacy_favicons {
+            let (width, height) = (favicon.width(), favicon.height());
+            let file_name = format!("apple-touch-icon-{width}x{height}.png");
+            let file = out_dir.join(&file_name);
+            resample::quantize(favicon, opt.dither)
+                .save(&file)
+                .with_context(|| format!("could not write the image to {}", file.display()))?;
+            if file_name != "apple-touch-icon-180x180.png" {
+                apple_legacy_links.push(format!(
+                    r#"<link rel="apple-touch-icon" sizes="{width}x{height}" href="/{file_name}" />"#
+                ));
+            }
+        }
+        let precomposed_file = out_dir.join("apple-touch-icon-precomposed.png");
+        resample::quantize(&apple_favicon, opt.dither)
+            .save(&precomposed_file)
+            .with_context(|| {
+                format!(
+                    "could not write the image to {}",
+                    precomposed_file.display()
+                )
+            })?;
+        apple_legacy_links.push(String::from(APPLE_PRECOMPOSED_HTML));
+    }
+
     for favicon in png_favicons {
         let file = out_dir.join(format!(
             "favicon-{}x{}.png",
             favicon.width(),
             favicon.height()
         ));
-        favicon
+        resample::quantize(&favicon, opt.dither)
             .save(&file)
             .with_context(|| format!("could not write the image to {}", file.display()))?;
     }
@@ -149,6 +362,122 @@ pub fn run() -> anyhow::Result<()> {
     fs::write(&ico_file, ico_favicon)
         .with_context(|| format!("could not write the image to {}", ico_file.display()))?;
 
+    if let Some(ref animated_favicon) = animated_favicon {
+        let animated_file = out_dir.join("favicon-animated.png");
+        fs::write(&animated_file, animated_favicon)
+            .with_context(|| format!("could not write the image to {}", animated_file.display()))?;
+    }
+
+    if let Some(app_ico) = app_ico {
+        let app_ico_file = out_dir.join("app.ico");
+        fs::write(&app_ico_file, app_ico)
+            .with_context(|| format!("could not write the image to {}", app_ico_file.display()))?;
+    }
+
+    let tile_color = opt
+        .tile_color
+        .as_ref()
+        .unwrap_or(&opt.theme_color)
+        .to_hex_string();
+    if let Some(ref microsoft_tiles) = microsoft_tiles {
+        let mut logos = Vec::with_capacity(microsoft_tiles.len());
+        for tile in microsoft_tiles {
+            let (width, height) = (tile.width(), tile.height());
+            let file = out_dir.join(format!("mstile-{width}x{height}.png"));
+            resample::quantize(tile, opt.dither)
+                .save(&file)
+                .with_context(|| format!("could not write the image to {}", file.display()))?;
+            let shape = if width == height { "square" } else { "wide" };
+            logos.push(format!(
+                r#"      <{shape}{width}x{height}logo src="/mstile-{width}x{height}.png" />"#
+            ));
+        }
+        let browserconfig = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                '\n',
+                "<browserconfig>\n",
+                "  <msapplication>\n",
+                "    <tile>\n",
+                "{}\n",
+                "      <TileColor>{}</TileColor>\n",
+                "    </tile>\n",
+                "  </msapplication>\n",
+                "</browserconfig>\n"
+            ),
+            logos.join("\n"),
+            tile_color
+        );
+        let browserconfig_file = out_dir.join("browserconfig.xml");
+        fs::write(&browserconfig_file, browserconfig).with_context(|| {
+            format!(
+                "could not write the browser configuration to {}",
+                browserconfig_file.display()
+            )
+        })?;
+    }
+
+    let mut apple_startup_links = Vec::new();
+    if is_apple_startup {
+        // The startup images are large, so each image is saved before the next
+        // one is created:
+        for (device_width, device_height, ratio) in generate::APPLE_SCREENS {
+            for orientation in ["portrait", "landscape"] {
+                let (width, height) = if orientation == "portrait" {
+                    (device_width * ratio, device_height * ratio)
+                } else {
+                    (device_height * ratio, device_width * ratio)
+                };
+                let startup_image = generate::apple_startup_image(
+                    &resampler, width, height, background, opt.dither,
+                );
+                let file_name = format!("apple-touch-startup-image-{width}x{height}.png");
+                let file = out_dir.join(&file_name);
+                startup_image
+                    .save(&file)
+                    .with_context(|| format!("could not write the image to {}", file.display()))?;
+                apple_startup_links.push(format!(
+                    concat!(
+                        r#"<link rel="apple-touch-startup-image" media="screen and "#,
+                        "(device-width: {}px) and (device-height: {}px) and ",
+                        "(-webkit-device-pixel-ratio: {}) and (orientation: {})",
+                        r#"" href="/{}" />"#
+                    ),
+                    device_width, device_height, ratio, orientation, file_name
+                ));
+            }
+        }
+    }
+
+    if let Some(ref mask_icon) = mask_icon {
+        let mask_icon_file = out_dir.join("safari-pinned-tab.svg");
+        fs::write(&mask_icon_file, mask_icon).with_context(|| {
+            format!("could not write the image to {}", mask_icon_file.display())
+        })?;
+    }
+
+    if let Some(ref linux_icons) = linux_icons {
+        for icon in linux_icons {
+            let dir = out_dir.join(format!(
+                "icons/hicolor/{}x{}/apps",
+                icon.width(),
+                icon.height()
+            ));
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+            let file = dir.join(format!("{}.png", opt.icon_name));
+            resample::quantize(icon, opt.dither)
+                .save(&file)
+                .with_context(|| format!("could not write the image to {}", file.display()))?;
+        }
+    }
+
+    if let Some(icns_icon) = icns_icon {
+        let icns_file = out_dir.join("icon.icns");
+        fs::write(&icns_file, icns_icon)
+            .with_context(|| format!("could not write the image to {}", icns_file.display()))?;
+    }
+
     let webmanifest =
         serde_json::to_string_pretty(&webmanifest).context("could not serialize as JSON")?;
     let webmanifest_file = out_dir.join("site.webmanifest");
@@ -165,13 +494,54 @@ pub fn run() -> anyhow::Result<()> {
     ));
 
     println!("\n");
+    let mut html = String::from(HTML);
+    if animated_favicon.is_some() {
+        writeln!(html, "{ANIMATED_HTML}")?;
+    }
+    for link in apple_legacy_links {
+        writeln!(html, "{link}")?;
+    }
+    for link in apple_startup_links {
+        writeln!(html, "{link}")?;
+    }
+    if mask_icon.is_some() {
+        let color = opt
+            .mask_icon_color
+            .as_ref()
+            .unwrap_or(&opt.theme_color)
+            .to_hex_string();
+        writeln!(
+            html,
+            r#"<link rel="mask-icon" href="/safari-pinned-tab.svg" color="{color}" />"#
+        )?;
+    }
+    if microsoft_tiles.is_some() {
+        writeln!(
+            html,
+            r#"<meta name="msapplication-TileColor" content="{tile_color}" />"#
+        )?;
+        html.push_str(MICROSOFT_HTML);
+    }
     println!("Copy the following and paste them into the <head> of your HTML.");
     PrettyPrinter::new()
-        .input_from_bytes(HTML.as_bytes())
+        .input_from_bytes(html.as_bytes())
         .language("html")
         .line_numbers(true)
         .grid(true)
         .print()
         .context("could not pretty-print HTML")?;
+
+    if linux_icons.is_some() {
+        let desktop_entry = format!("Icon={}\n", opt.icon_name);
+        println!("\n");
+        println!("Copy the following and paste it into the desktop entry of your application.");
+        PrettyPrinter::new()
+            .input_from_bytes(desktop_entry.as_bytes())
+            .language("ini")
+            .line_numbers(true)
+            .grid(true)
+            .print()
+            .context("could not pretty-print the desktop entry")?;
+    }
     Ok(())
 }
@@ -10,7 +10,13 @@ use std::{
 use clap::{CommandFactory, Parser, ValueEnum, ValueHint};
 use clap_complete::Generator;
 use csscolorparser::Color;
-use image::{ImageError, ImageFormat, imageops::FilterType};
+use image::{ImageError, ImageFormat};
+
+use crate::{
+    generate::Shape,
+    preprocess::{self, FrameSelection},
+    resample,
+};
 
 const LONG_VERSION: &str = concat!(
     env!("CARGO_PKG_VERSION"),
@@ -29,7 +35,7 @@ const LONG_VERSION: &str = concat!(
 const AFTER_LONG_HELP: &str = "See `favico(1)` for more details.";
 
 #[derive(Debug, Parser)]
-#[allow(clippy::doc_markdown)]
+#[allow(clippy::doc_markdown, clippy::struct_excessive_bools)]
 #[command(
     version,
     long_version(LONG_VERSION),
@@ -52,9 +58,73 @@ pub struct Opt {
     pub output: PathBuf,
 
     /// Store PNG images instead of BMP images to an ICO image.
-    #[arg(long)]
+    #[arg(long, conflicts_with("ico_png_threshold"))]
     pub png: bool,
 
+    /// Sizes of the images stored in an ICO image.
+    ///
+    /// <SIZE> takes a comma-separated list of sizes between 1 and 256.
+    #[arg(
+        long,
+        value_delimiter(','),
+        default_value("16,32,48"),
+        value_name("SIZE"),
+        value_parser(clap::value_parser!(u32).range(1..=256))
+    )]
+    pub ico_sizes: Vec<u32>,
+
+    /// Store PNG images to an ICO image if their size is at least <SIZE>.
+    ///
+    /// The smaller images are stored as BMP images.
+    #[arg(
+        long,
+        value_name("SIZE"),
+        value_parser(clap::value_parser!(u32).range(1..=256))
+    )]
+    pub ico_png_threshold: Option<u32>,
+
+    /// Also store 4-bit and 8-bit paletted BMP images to an ICO image.
+    ///
+    /// These images have a 1-bit transparency mask, and are intended for
+    /// legacy applications which do not support 32-bit images. They are not
+    /// stored for the sizes which are stored as PNG images.
+    #[arg(long)]
+    pub ico_compat: bool,
+
+    /// Also generate an ICNS image for macOS applications.
+    ///
+    /// The ICNS image contains the 16x16@2x and 32x32@2x images, and the
+    /// 128x128, 256x256 and 512x512 images with their @2x versions. It is
+    /// output as "icon.icns".
+    #[arg(long)]
+    pub icns: bool,
+
+    /// Also generate an animated PNG favicon from the animated input image.
+    ///
+    /// All the frames of the input image are resized to 32x32, and are output
+    /// as "favicon-animated.png". Firefox animates this favicon. This cannot
+    /// be used with '--trim'.
+    #[arg(long, conflicts_with("trim"))]
+    pub apng: bool,
+
+    /// Additional targets to generate.
+    ///
+    /// <TARGET> takes a comma-separated list of targets.
+    #[arg(long, value_enum, value_delimiter(','), value_name("TARGET"))]
+    pub target: Vec<Target>,
+
+    /// Name of the icon for the Linux icon theme.
+    ///
+    /// This is used as the filename of the icons, and as the value of the Icon
+    /// key in the desktop entry. <NAME> cannot contain path separators.
+    #[arg(
+        long,
+        default_value("icon"),
+        value_name("NAME"),
+        value_parser(parse_icon_name)
+    )]
+    pub icon_name: String,
+
     /// Set the name member of the web app manifest.
     #[arg(long, default_value_t, value_name("NAME"))]
     pub name: String,
@@ -75,10 +145,155 @@ pub struct Opt {
     /// Set the background_color member of the web app manifest.
     ///
     /// <COLOR> takes a CSS color string. Note that <COLOR> is converted to the
-    /// sRGB color space, so a lossy conversion may be performed.
+    /// sRGB color space, so a lossy conversion may be performed. This is also
+    /// used as the background color of the icon sets specified in '--flatten'
+    /// and the startup images for iOS and iPadOS.
     #[arg(long, default_value("#ffffff"), value_name("COLOR"))]
     pub background_color: Color,
 
+    /// Icon sets to flatten onto the background color.
+    ///
+    /// The transparent pixels of the icons in these icon sets are composited
+    /// over the background color specified in '--background-color'. <ICON_SET>
+    /// takes a comma-separated list of icon sets.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter(','),
+        default_value("apple"),
+        value_name("ICON_SET"),
+        conflicts_with("no_flatten")
+    )]
+    pub flatten: Vec<IconSet>,
+
+    /// Do not flatten any icon sets onto the background color.
+    #[arg(long)]
+    pub no_flatten: bool,
+
+    /// Padding around the artwork.
+    ///
+    /// <PERCENT> is the ratio of the padding on each side to the size of the
+    /// icons, and takes a value between 0% and 50% (exclusive). This is applied
+    /// to the icon sets for which the padding is not specified individually.
+    #[arg(
+        long,
+        default_value("0%"),
+        value_name("PERCENT"),
+        value_parser(parse_padding)
+    )]
+    pub padding: f32,
+
+    /// Padding around the artwork of the favicons for Android.
+    #[arg(long, value_name("PERCENT"), value_parser(parse_padding))]
+    pub android_padding: Option<f32>,
+
+    /// Padding around the artwork of the favicons for iOS and iPadOS.
+    #[arg(long, value_name("PERCENT"), value_parser(parse_padding))]
+    pub apple_padding: Option<f32>,
+
+    /// Padding around the artwork of the PNG favicons and the ICO favicon.
+    #[arg(long, value_name("PERCENT"), value_parser(parse_padding))]
+    pub favicon_padding: Option<f32>,
+
+    /// Padding around the artwork of the Microsoft tiles.
+    #[arg(long, value_name("PERCENT"), value_parser(parse_padding))]
+    pub microsoft_padding: Option<f32>,
+
+    /// Shape of the favicons for Android, the PNG favicons and the ICO favicon.
+    ///
+    /// The anti-aliased alpha mask of <SHAPE> is applied to these favicons.
+    /// <SHAPE> takes "square", "rounded:<RADIUS>", "circle" or "squircle".
+    /// <RADIUS> is the ratio of the radius of the corners to the size of the
+    /// favicons, and takes a value between 0% and 50% (inclusive). This is not
+    /// applied to the favicons for iOS and iPadOS, because iOS applies its own
+    /// mask.
+    #[arg(
+        long,
+        default_value("square"),
+        value_name("SHAPE"),
+        value_parser(parse_shape)
+    )]
+    pub shape: Shape,
+
+    /// Set the tile color of the Microsoft tiles.
+    ///
+    /// <COLOR> takes a CSS color string. If <COLOR> is not specified, the color
+    /// specified in '--theme-color' will be set.
+    #[arg(long, value_name("COLOR"))]
+    pub tile_color: Option<Color>,
+
+    /// Set the color of the Safari pinned tab icon.
+    ///
+    /// <COLOR> takes a CSS color string. If <COLOR> is not specified, the color
+    /// specified in '--theme-color' will be set.
+    #[arg(long, value_name("COLOR"))]
+    pub mask_icon_color: Option<Color>,
+
+    /// Do not rotate or flip the input image according to its orientation.
+    ///
+    /// By default, the orientation stored in the Exif metadata of the input
+    /// image is applied before checking whether the input image is square.
+    #[arg(long)]
+    pub no_auto_orient: bool,
+
+    /// Frame of the animated input image to use.
+    ///
+    /// <FRAME> takes the index of the frame starting from 0, or "best". If
+    /// <FRAME> is "best", the frame with the highest contrast is used. If this
+    /// is not specified, the first frame is used. This is supported for the
+    /// animated GIF, WebP and PNG images.
+    #[arg(long, value_name("FRAME"), value_parser(parse_frame))]
+    pub frame: Option<FrameSelection>,
+
+    /// Layer or group of the PSD input image to use.
+    ///
+    /// <PATH> takes the names of the groups and the layer separated by "/",
+    /// such as "Logo/Mark". The layer or the group is used even if it is
+    /// hidden. If this is not specified, the composite image is used.
+    #[arg(long, value_name("PATH"))]
+    pub layer: Option<String>,
+
+    /// Trim the transparent borders of the input image.
+    ///
+    /// The input image is cropped to the bounding box of the pixels whose alpha
+    /// is greater than <ALPHA>, and is placed in the center of the transparent
+    /// square canvas. <ALPHA> takes a value between 0 and 255. If <ALPHA> is
+    /// not specified, it is 0.
+    #[arg(
+        long,
+        value_name("ALPHA"),
+        num_args(0..=1),
+        require_equals(true),
+        default_missing_value("0")
+    )]
+    pub trim: Option<u8>,
+
+    /// Tone mapping operator used to convert the HDR input image.
+    ///
+    /// This is applied to the Radiance RGBE and OpenEXR images, which have the
+    /// colors brighter than white, before converting them to 8 bits per
+    /// channel.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        value_name("OPERATOR"),
+        ignore_case(true)
+    )]
+    pub tone_map: ToneMap,
+
+    /// Exposure adjustment of the HDR input image, in stops.
+    ///
+    /// The colors of the HDR input image are multiplied by 2 to the power of
+    /// <EV> before tone mapping.
+    #[arg(
+        long,
+        default_value("0"),
+        value_name("EV"),
+        allow_negative_numbers(true)
+    )]
+    pub exposure: f32,
+
     /// Sampling filter used to resize the input image.
     #[arg(
         long,
@@ -89,6 +304,53 @@ pub struct Opt {
     )]
     pub filter: Filter,
 
+    /// Number of the lobes of the Lanczos filter.
+    ///
+    /// This is used when '--filter lanczos' is specified. <N> takes a value
+    /// between 1 and 8.
+    #[arg(
+        long,
+        default_value("3"),
+        value_name("N"),
+        value_parser(clap::value_parser!(u8).range(1..=8))
+    )]
+    pub lanczos_lobes: u8,
+
+    /// Downscale the input image by halving it repeatedly.
+    ///
+    /// The input image is halved repeatedly with the box filter in advance,
+    /// and each image is resized from the smallest halved image which is not
+    /// smaller than it with <FILTER>. The halved images are shared by all
+    /// images, so the input image is not resized from the full resolution for
+    /// each image. The halved images take about a third more memory in
+    /// addition to the input image.
+    #[arg(long)]
+    pub progressive: bool,
+
+    /// Resize the input image in linear light.
+    ///
+    /// The input image is converted from sRGB to linear light before
+    /// resizing, and converted back after resizing. This prevents thin strokes
+    /// and edges from getting darker when the image is downscaled.
+    #[arg(long)]
+    pub linear: bool,
+
+    /// Sharpen the PNG favicons and the ICO favicon after resizing.
+    ///
+    /// The unsharp mask is applied to these favicons. The favicons smaller
+    /// than 48x48 are sharpened more strongly, because they get softer by
+    /// downscaling.
+    #[arg(long)]
+    pub sharpen: bool,
+
+    /// Dither the generated images when quantizing them to 8 bits per channel.
+    ///
+    /// The generated images are processed in 32-bit floating point, and are
+    /// quantized to 8 bits per channel only when they are encoded. The ordered
+    /// dithering prevents the smooth gradients from getting banded.
+    #[arg(long)]
+    pub dither: bool,
+
     /// The format of the input.
     ///
     /// If <FORMAT> is not specified, the format is determined based on the
@@ -109,12 +371,38 @@ pub struct Opt {
     /// the formats supported by the image crate. The format of [IMAGE] is
     /// determined based on the extension or the magic number if possible. If
     /// the format cannot be determined, use '--format'. Note that [IMAGE] must
-    /// be square.
+    /// be square. If [IMAGE] has an embedded ICC profile, it is converted to
+    /// sRGB.
     #[arg(value_name("IMAGE"), value_hint(ValueHint::FilePath))]
     pub input: Option<PathBuf>,
 }
 
 impl Opt {
+    /// Returns the ratio of the padding of `icon_set`.
+    pub fn padding(&self, icon_set: IconSet) -> f32 {
+        match icon_set {
+            IconSet::Android => self.android_padding,
+            IconSet::Apple => self.apple_padding,
+            IconSet::Favicon => self.favicon_padding,
+            IconSet::Microsoft => self.microsoft_padding,
+        }
+        .unwrap_or(self.padding)
+    }
+
+    /// Returns the sampling filter used to resize the input image.
+    pub const fn sampling_filter(&self) -> resample::Filter {
+        match self.filter {
+            Filter::Nearest => resample::Filter::Nearest,
+            Filter::Box => resample::Filter::Box,
+            Filter::Triangle => resample::Filter::Triangle,
+            Filter::CatmullRom => resample::Filter::CatmullRom,
+            Filter::Mitchell => resample::Filter::Mitchell,
+            Filter::Gaussian => resample::Filter::Gaussian,
+            Filter::Lanczos3 => resample::Filter::Lanczos(3),
+            Filter::Lanczos => resample::Filter::Lanczos(self.lanczos_lobes),
+        }
+    }
+
     /// Generates shell completion and print it.
     pub fn print_completion(generator: impl Generator) {
         clap_complete::generate(
@@ -174,12 +462,69 @@ impl Generator for Shell {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Target {
+    /// Windows application icon.
+    ///
+    /// This is output as "app.ico", and contains the images from 16x16 to
+    /// 256x256.
+    AppIco,
+
+    /// Linux hicolor icon theme.
+    ///
+    /// This is output to the "icons/hicolor" directory, and contains the
+    /// images from 16x16 to 512x512.
+    Hicolor,
+
+    /// Microsoft tiles for pinned sites.
+    ///
+    /// This outputs the "mstile-*.png" images and "browserconfig.xml".
+    Microsoft,
+
+    /// Safari pinned tab icon.
+    ///
+    /// This is output as "safari-pinned-tab.svg", and is created by tracing
+    /// the alpha silhouette of the input image.
+    MaskIcon,
+
+    /// Startup images for iOS and iPadOS.
+    ///
+    /// This outputs the "apple-touch-startup-image-*.png" images for the
+    /// common screen sizes in both orientations. The icon is placed in the
+    /// center of the background color specified in '--background-color'.
+    AppleStartup,
+
+    /// Favicons for the legacy versions of iOS.
+    ///
+    /// This outputs the "apple-touch-icon-<N>x<N>.png" images from 57x57 to
+    /// 180x180, and "apple-touch-icon-precomposed.png".
+    AppleLegacy,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum IconSet {
+    /// Favicons for Android.
+    Android,
+
+    /// Favicons for iOS and iPadOS, including the legacy ones.
+    Apple,
+
+    /// The PNG favicons and the ICO favicon.
+    Favicon,
+
+    /// Microsoft tiles.
+    Microsoft,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
 #[value(rename_all = "lower")]
 pub enum Filter {
     /// Nearest Neighbor.
     Nearest,
 
+    /// Box Filter.
+    Box,
+
     /// Linear Filter.
     Triangle,
 
@@ -187,26 +532,48 @@ pub enum Filter {
     #[default]
     CatmullRom,
 
+    /// Mitchell-Netravali Filter.
+    Mitchell,
+
     /// Gaussian Filter.
     Gaussian,
 
     /// Lanczos with window 3.
     Lanczos3,
+
+    /// Lanczos with the window of '--lanczos-lobes'.
+    Lanczos,
 }
 
-impl From<Filter> for FilterType {
-    fn from(filter: Filter) -> Self {
-        match filter {
-            Filter::Nearest => Self::Nearest,
-            Filter::Triangle => Self::Triangle,
-            Filter::CatmullRom => Self::CatmullRom,
-            Filter::Gaussian => Self::Gaussian,
-            Filter::Lanczos3 => Self::Lanczos3,
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "lower")]
+pub enum ToneMap {
+    /// Reinhard operator.
+    ///
+    /// This compresses the highlights smoothly.
+    #[default]
+    Reinhard,
+
+    /// Filmic curve approximating the ACES reference rendering transform.
+    ///
+    /// This gives more contrast than Reinhard.
+    Aces,
+
+    /// Clip the colors brighter than white.
+    Clip,
+}
+
+impl From<ToneMap> for preprocess::ToneMap {
+    fn from(operator: ToneMap) -> Self {
+        match operator {
+            ToneMap::Reinhard => Self::Reinhard,
+            ToneMap::Aces => Self::Aces,
+            ToneMap::Clip => Self::Clip,
         }
     }
 }
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Copy, Debug, ValueEnum)]
 #[allow(clippy::doc_markdown)]
 #[value(rename_all = "lower")]
 pub enum Format {
@@ -249,6 +616,10 @@ pub enum Format {
     #[cfg(feature = "pnm")]
     Pnm,
 
+    /// Adobe Photoshop Document.
+    #[cfg(feature = "psd")]
+    Psd,
+
     /// Quite OK Image Format.
     #[cfg(feature = "qoi")]
     Qoi,
@@ -300,6 +671,10 @@ impl TryFrom<Format> for ImageFormat {
             Format::Tiff => Ok(Self::Tiff),
             #[cfg(feature = "webp")]
             Format::WebP => Ok(Self::WebP),
+            #[cfg(feature = "psd")]
+            Format::Psd => Err(Self::Error::Unsupported(
+                image::error::ImageFormatHint::Unknown.into(),
+            )),
             #[cfg(feature = "xbm")]
             Format::Xbm => Err(Self::Error::Unsupported(
                 image::error::ImageFormatHint::Unknown.into(),
@@ -308,6 +683,62 @@ impl TryFrom<Format> for ImageFormat {
     }
 }
 
+fn parse_padding(padding: &str) -> Result<f32, String> {
+    let padding = padding
+        .strip_suffix('%')
+        .unwrap_or(padding)
+        .parse::<f32>()
+        .map_err(|err| err.to_string())?;
+    if (0.0..50.0).contains(&padding) {
+        Ok(padding / 100.0)
+    } else {
+        Err(String::from("padding is not in 0%..50%"))
+    }
+}
+
+fn parse_shape(shape: &str) -> Result<Shape, String> {
+    match shape {
+        "square" => Ok(Shape::Square),
+        "circle" => Ok(Shape::Circle),
+        "squircle" => Ok(Shape::Squircle),
+        shape => {
+            let radius = shape
+                .strip_prefix("rounded:")
+                .ok_or_else(|| String::from("unknown shape"))?;
+            let radius = radius
+                .strip_suffix('%')
+                .unwrap_or(radius)
+                .parse::<f32>()
+                .map_err(|err| err.to_string())?;
+            if (0.0..=50.0).contains(&radius) {
+                Ok(Shape::Rounded(radius / 100.0))
+            } else {
+                Err(String::from("radius is not in 0%..=50%"))
+            }
+        }
+    }
+}
+
+fn parse_frame(frame: &str) -> Result<FrameSelection, String> {
+    match frame {
+        "best" => Ok(FrameSelection::Best),
+        index => index
+            .parse()
+            .map(FrameSelection::Index)
+            .map_err(|err| err.to_string()),
+    }
+}
+
+fn parse_icon_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(format!("'{name}' is not a valid file name"));
+    }
+    if name.contains(std::path::is_separator) {
+        return Err(String::from("name contains a path separator"));
+    }
+    Ok(String::from(name))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,18 +758,101 @@ mod tests {
         assert_eq!(Shell::Zsh.file_name("favico"), "_favico");
     }
 
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn parse_padding() {
+        assert_eq!(super::parse_padding("0%").unwrap(), 0.0);
+        assert_eq!(super::parse_padding("12%").unwrap(), 0.12);
+        assert_eq!(super::parse_padding("12.5").unwrap(), 0.125);
+        assert!(super::parse_padding("50%").is_err());
+        assert!(super::parse_padding("-1%").is_err());
+        assert!(super::parse_padding("a").is_err());
+    }
+
+    #[test]
+    fn parse_shape() {
+        assert_eq!(super::parse_shape("square").unwrap(), Shape::Square);
+        assert_eq!(
+            super::parse_shape("rounded:12%").unwrap(),
+            Shape::Rounded(0.12)
+        );
+        assert_eq!(
+            super::parse_shape("rounded:50").unwrap(),
+            Shape::Rounded(0.5)
+        );
+        assert_eq!(super::parse_shape("circle").unwrap(), Shape::Circle);
+        assert_eq!(super::parse_shape("squircle").unwrap(), Shape::Squircle);
+        assert!(super::parse_shape("rounded").is_err());
+        assert!(super::parse_shape("rounded:51%").is_err());
+        assert!(super::parse_shape("a").is_err());
+    }
+
+    #[test]
+    fn parse_frame() {
+        assert_eq!(super::parse_frame("0").unwrap(), FrameSelection::Index(0));
+        assert_eq!(super::parse_frame("12").unwrap(), FrameSelection::Index(12));
+        assert_eq!(super::parse_frame("best").unwrap(), FrameSelection::Best);
+        assert!(super::parse_frame("-1").is_err());
+        assert!(super::parse_frame("a").is_err());
+    }
+
+    #[test]
+    fn parse_icon_name() {
+        assert_eq!(super::parse_icon_name("icon").unwrap(), "icon");
+        assert_eq!(
+            super::parse_icon_name("org.example.App").unwrap(),
+            "org.example.App"
+        );
+        assert!(super::parse_icon_name("").is_err());
+        assert!(super::parse_icon_name(".").is_err());
+        assert!(super::parse_icon_name("..").is_err());
+        assert!(super::parse_icon_name("../../x").is_err());
+        assert!(super::parse_icon_name("a/b").is_err());
+    }
+
     #[test]
     fn default_filter() {
         assert_eq!(Filter::default(), Filter::CatmullRom);
     }
 
     #[test]
-    fn from_filter_to_filter_type() {
-        assert_eq!(FilterType::from(Filter::Nearest), FilterType::Nearest);
-        assert_eq!(FilterType::from(Filter::Triangle), FilterType::Triangle);
-        assert_eq!(FilterType::from(Filter::CatmullRom), FilterType::CatmullRom);
-        assert_eq!(FilterType::from(Filter::Gaussian), FilterType::Gaussian);
-        assert_eq!(FilterType::from(Filter::Lanczos3), FilterType::Lanczos3);
+    fn sampling_filter() {
+        let filter = |args: &[&str]| {
+            Opt::try_parse_from([&["favico", "-o", "out"], args].concat())
+                .unwrap()
+                .sampling_filter()
+        };
+        assert_eq!(filter(&[]), resample::Filter::CatmullRom);
+        assert_eq!(filter(&["--filter", "nearest"]), resample::Filter::Nearest);
+        assert_eq!(filter(&["--filter", "box"]), resample::Filter::Box);
+        assert_eq!(
+            filter(&["--filter", "triangle"]),
+            resample::Filter::Triangle
+        );
+        assert_eq!(
+            filter(&["--filter", "mitchell"]),
+            resample::Filter::Mitchell
+        );
+        assert_eq!(
+            filter(&["--filter", "gaussian"]),
+            resample::Filter::Gaussian
+        );
+        assert_eq!(
+            filter(&["--filter", "lanczos3"]),
+            resample::Filter::Lanczos(3)
+        );
+        assert_eq!(
+            filter(&["--filter", "lanczos"]),
+            resample::Filter::Lanczos(3)
+        );
+        assert_eq!(
+            filter(&["--filter", "lanczos", "--lanczos-lobes", "5"]),
+            resample::Filter::Lanczos(5)
+        );
+        assert_eq!(
+            filter(&["--filter", "lanczos3", "--lanczos-lobes", "5"]),
+            resample::Filter::Lanczos(3)
+        );
     }
 
     #[test]
@@ -410,6 +924,8 @@ mod tests {
             ImageFormat::try_from(Format::WebP).unwrap(),
             ImageFormat::WebP
         );
+        #[cfg(feature = "psd")]
+        assert!(ImageFormat::try_from(Format::Psd).is_err());
         #[cfg(feature = "xbm")]
         assert!(ImageFormat::try_from(Format::Xbm).is_err());
     }
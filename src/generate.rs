@@ -2,58 +2,415 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::io::Cursor;
+use std::sync::Arc;
 
-use image::{
-    ColorType, DynamicImage, ImageFormat, ImageResult,
-    codecs::ico::{IcoEncoder, IcoFrame},
-    imageops::FilterType,
+use image::{Delay, DynamicImage, ImageResult, Rgba, Rgba32FImage, RgbaImage, imageops};
+use png::EncodingError;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    apng,
+    icns::{self, IcnsElement},
+    ico::{self, BitDepth, IcoFrame},
+    resample::{self, Filter, Resampler},
+    trace,
 };
 
+// The standard deviation of the Gaussian blur of the unsharp mask, in pixels:
+const SHARPEN_SIGMA: f32 = 0.6;
+
+/// Options for processing the icons of an icon set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Style {
+    /// The background color to flatten the icons onto.
+    pub background: Option<Rgba<u8>>,
+
+    /// The ratio of the padding on each side to the size of the icons.
+    pub padding: f32,
+
+    /// The shape of the icons.
+    pub shape: Shape,
+
+    /// Whether to sharpen the icons after resizing.
+    pub sharpen: bool,
+}
+
+/// The shape of the icons.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Shape {
+    /// Square.
+    #[default]
+    Square,
+
+    /// Square with rounded corners.
+    ///
+    /// The value is the ratio of the radius of the corners to the size of the
+    /// icons.
+    Rounded(f32),
+
+    /// Circle.
+    Circle,
+
+    /// Superellipse with the exponent of 4.
+    Squircle,
+}
+
+impl Shape {
+    /// Returns `true` if the point is inside the shape.
+    ///
+    /// The point is in the coordinate system where the shape fits in the
+    /// square from (-1, -1) to (1, 1).
+    fn contains(self, x: f32, y: f32) -> bool {
+        match self {
+            Self::Square => true,
+            Self::Rounded(radius) => {
+                let radius = radius * 2.0;
+                let (dx, dy) = (
+                    (x.abs() - (1.0 - radius)).max(0.0),
+                    (y.abs() - (1.0 - radius)).max(0.0),
+                );
+                dx.hypot(dy) <= radius
+            }
+            Self::Circle => x.hypot(y) <= 1.0,
+            Self::Squircle => x.powi(4) + y.powi(4) <= 1.0,
+        }
+    }
+}
+
 /// Creates favicons for Android.
-pub fn for_android(image: &DynamicImage, filter: FilterType) -> Vec<DynamicImage> {
-    let mut favicons = vec![DynamicImage::default(); 2];
+pub fn for_android(resampler: &Resampler, style: Style) -> Vec<DynamicImage> {
     let sizes = [192, 512];
-    for (i, size) in sizes.iter().enumerate() {
-        favicons[i] = image.resize(*size, *size, filter);
-    }
-    favicons
+    sizes
+        .into_par_iter()
+        .map(|size| resize(resampler, size, style))
+        .collect()
 }
 
+/// Screen sizes of iPhone and iPad in CSS pixels, and their device pixel
+/// ratios.
+pub const APPLE_SCREENS: [(u32, u32, u32); 17] = [
+    (320, 568, 2),
+    (375, 667, 2),
+    (414, 736, 3),
+    (375, 812, 3),
+    (414, 896, 2),
+    (414, 896, 3),
+    (390, 844, 3),
+    (428, 926, 3),
+    (393, 852, 3),
+    (430, 932, 3),
+    (744, 1133, 2),
+    (768, 1024, 2),
+    (810, 1080, 2),
+    (834, 1112, 2),
+    (820, 1180, 2),
+    (834, 1194, 2),
+    (1024, 1366, 2),
+];
+
 /// Creates favicon for iOS.
-pub fn for_apple(image: &DynamicImage, filter: FilterType) -> DynamicImage {
+pub fn for_apple(resampler: &Resampler, style: Style) -> DynamicImage {
     let size = 180;
-    image.resize(size, size, filter)
+    resize(resampler, size, style)
+}
+
+/// Creates favicons for the legacy versions of iOS.
+pub fn for_apple_legacy(resampler: &Resampler, style: Style) -> Vec<DynamicImage> {
+    let sizes = [57, 60, 72, 76, 114, 120, 144, 152, 167, 180];
+    sizes
+        .into_par_iter()
+        .map(|size| resize(resampler, size, style))
+        .collect()
+}
+
+/// Creates startup image for iOS on the canvas filled with `background`.
+pub fn apple_startup_image(
+    resampler: &Resampler,
+    width: u32,
+    height: u32,
+    background: Rgba<u8>,
+    dither: bool,
+) -> RgbaImage {
+    let size = width.min(height) / 3;
+    let icon = resample::quantize(&resampler.resize(size), dither);
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    imageops::overlay(
+        &mut canvas,
+        &icon,
+        i64::from((width - size) / 2),
+        i64::from((height - size) / 2),
+    );
+    canvas
+}
+
+/// Creates tiles for Windows.
+pub fn for_microsoft(resampler: &Resampler, style: Style) -> Vec<DynamicImage> {
+    let sizes = [(70, 70), (150, 150), (310, 150), (310, 310)];
+    sizes
+        .into_par_iter()
+        .map(|(width, height)| {
+            let size = width.min(height);
+            let tile = resize(resampler, size, style);
+            if width == height {
+                return tile;
+            }
+            let background = style.background.unwrap_or(Rgba([0; 4]));
+            let mut canvas = Rgba32FImage::from_pixel(width, height, to_rgba32f(background));
+            imageops::overlay(
+                &mut canvas,
+                &tile.into_rgba32f(),
+                i64::from((width - size) / 2),
+                i64::from((height - size) / 2),
+            );
+            canvas.into()
+        })
+        .collect()
+}
+
+/// Creates monochrome SVG icon for Safari pinned tabs from the alpha channel.
+pub fn mask_icon(resampler: &Resampler) -> String {
+    let size = resampler.size().min(512);
+    let image = resampler.resize(size).to_rgba8();
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}">"#,
+            r#"<path d="{path}" />"#,
+            "</svg>\n"
+        ),
+        size = size,
+        path = trace::trace(&image)
+    )
 }
 
 /// Creates PNG favicons.
-pub fn png_favicons(image: &DynamicImage, filter: FilterType) -> Vec<DynamicImage> {
-    let mut favicons = vec![DynamicImage::default(); 2];
+pub fn png_favicons(resampler: &Resampler, style: Style) -> Vec<DynamicImage> {
     let sizes = [16, 32];
-    for (i, size) in sizes.iter().enumerate() {
-        favicons[i] = image.resize(*size, *size, filter);
-    }
-    favicons
+    sizes
+        .into_par_iter()
+        .map(|size| resize(resampler, size, style))
+        .collect()
+}
+
+/// Creates animated PNG favicon from the frames and their delays.
+///
+/// Each frame is resized one at a time, and its source image is dropped after
+/// that.
+pub fn animated_favicon(
+    frames: Vec<(DynamicImage, Delay)>,
+    filter: Filter,
+    linear: bool,
+    dither: bool,
+    style: Style,
+) -> Result<Vec<u8>, EncodingError> {
+    let size = 32;
+    let frames = frames
+        .into_iter()
+        .map(|(image, delay)| {
+            let frame = resize_with(
+                |size| Arc::new(resample::resize(image, size, filter, linear)),
+                size,
+                style,
+            );
+            (resample::quantize(&frame, dither), delay)
+        })
+        .collect::<Vec<_>>();
+    apng::encode(&frames)
 }
 
 /// Creates ICO favicon.
-pub fn ico_favicon(image: &DynamicImage, filter: FilterType, is_png: bool) -> ImageResult<Vec<u8>> {
-    let mut frames = Vec::with_capacity(3);
-    let sizes = [16, 32, 48];
-    for size in sizes {
-        let favicon = image.resize(size, size, filter).into_rgba8();
-        let format = if is_png {
-            ImageFormat::Png
-        } else {
-            ImageFormat::Bmp
+///
+/// The images of `sizes` which are at least `png_threshold` are stored as PNG
+/// images. If `is_compat` is `true`, paletted BMP images are also stored.
+pub fn ico_favicon(
+    resampler: &Resampler,
+    sizes: &[u32],
+    png_threshold: Option<u32>,
+    is_compat: bool,
+    dither: bool,
+    style: Style,
+) -> ImageResult<Vec<u8>> {
+    let mut sizes = sizes.to_vec();
+    sizes.sort_unstable();
+    sizes.dedup();
+    let frames = sizes
+        .into_par_iter()
+        .map(|size| {
+            let favicon = resample::quantize(&resize(resampler, size, style), dither);
+            if png_threshold.is_some_and(|threshold| size >= threshold) {
+                return Ok(vec![IcoFrame::png(&favicon)?]);
+            }
+            let mut frames = Vec::with_capacity(3);
+            if is_compat {
+                frames.push(IcoFrame::bmp(&favicon, BitDepth::Four));
+                frames.push(IcoFrame::bmp(&favicon, BitDepth::Eight));
+            }
+            frames.push(IcoFrame::bmp(&favicon, BitDepth::ThirtyTwo));
+            Ok(frames)
+        })
+        .collect::<ImageResult<Vec<_>>>()?;
+    Ok(ico::encode(
+        &frames.into_iter().flatten().collect::<Vec<_>>(),
+    ))
+}
+
+/// Creates ICO icon for Windows applications.
+pub fn app_ico(resampler: &Resampler, dither: bool) -> ImageResult<Vec<u8>> {
+    let sizes = [16, 20, 24, 32, 40, 48, 64, 256];
+    ico_favicon(
+        resampler,
+        &sizes,
+        Some(256),
+        false,
+        dither,
+        Style::default(),
+    )
+}
+
+/// Creates icons for the Linux hicolor icon theme.
+pub fn for_linux(resampler: &Resampler) -> Vec<Arc<DynamicImage>> {
+    let sizes = [16, 22, 24, 32, 48, 64, 128, 256, 512];
+    sizes
+        .into_par_iter()
+        .map(|size| resampler.resize(size))
+        .collect()
+}
+
+/// Creates ICNS icon for macOS.
+pub fn icns_icon(resampler: &Resampler, dither: bool) -> ImageResult<Vec<u8>> {
+    let elements = [
+        (*b"ic11", 32),
+        (*b"ic12", 64),
+        (*b"ic07", 128),
+        (*b"ic08", 256),
+        (*b"ic13", 256),
+        (*b"ic09", 512),
+        (*b"ic14", 512),
+        (*b"ic10", 1024),
+    ];
+    let icons = elements
+        .into_par_iter()
+        .map(|(icon_type, size)| {
+            IcnsElement::png(
+                icon_type,
+                &resample::quantize(&resampler.resize(size), dither),
+            )
+        })
+        .collect::<ImageResult<Vec<_>>>()?;
+    Ok(icns::encode(&icons))
+}
+
+/// Resizes the source image to `size`, and processes it according to `style`.
+///
+/// If the padding is specified, the image is shrunk and placed in the center
+/// of the transparent canvas of `size`.
+fn resize(resampler: &Resampler, size: u32, style: Style) -> DynamicImage {
+    resize_with(|size| resampler.resize(size), size, style)
+}
+
+/// Resizes the image to `size` with `resize`, and processes it according to
+/// `style`.
+///
+/// `resize` is called once.
+fn resize_with(
+    resize: impl FnOnce(u32) -> Arc<DynamicImage>,
+    size: u32,
+    style: Style,
+) -> DynamicImage {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    let padding = (size as f32 * style.padding).round() as u32;
+    let image = if padding == 0 {
+        Arc::unwrap_or_clone(resize(size))
+    } else {
+        let inner_size = size.saturating_sub(padding * 2).max(1);
+        let mut canvas = Rgba32FImage::new(size, size);
+        imageops::overlay(
+            &mut canvas,
+            &resize(inner_size).to_rgba32f(),
+            i64::from((size - inner_size) / 2),
+            i64::from((size - inner_size) / 2),
+        );
+        canvas.into()
+    };
+    let image = if style.sharpen {
+        resample::sharpen(&image, SHARPEN_SIGMA, sharpen_amount(size))
+    } else {
+        image
+    };
+    let image = match style.background {
+        Some(background) => flatten(&image, background),
+        None => image,
+    };
+    mask(&image, style.shape)
+}
+
+/// Returns the amount of the unsharp mask for the icons of `size`.
+///
+/// The smaller icons are sharpened more strongly, because they get softer by
+/// downscaling.
+const fn sharpen_amount(size: u32) -> f32 {
+    match size {
+        ..32 => 1.0,
+        32..48 => 0.7,
+        _ => 0.4,
+    }
+}
+
+/// Applies the anti-aliased alpha mask of `shape` to `image`.
+fn mask(image: &DynamicImage, shape: Shape) -> DynamicImage {
+    // The number of the samples per pixel in each direction:
+    const SAMPLES: u16 = 4;
+
+    if shape == Shape::Square {
+        return image.clone();
+    }
+    let mut image = image.to_rgba32f();
+    #[allow(clippy::cast_precision_loss)]
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        #[allow(clippy::cast_precision_loss)]
+        let (x, y) = (x as f32, y as f32);
+        let mut coverage = 0_u16;
+        for sy in 0..SAMPLES {
+            for sx in 0..SAMPLES {
+                let sx = x + (f32::from(sx) + 0.5) / f32::from(SAMPLES);
+                let sy = y + (f32::from(sy) + 0.5) / f32::from(SAMPLES);
+                if shape.contains(
+                    (sx / width).mul_add(2.0, -1.0),
+                    (sy / height).mul_add(2.0, -1.0),
+                ) {
+                    coverage += 1;
+                }
+            }
+        }
+        pixel[3] *= f32::from(coverage) / f32::from(SAMPLES.pow(2));
+    }
+    image.into()
+}
+
+/// Composites `image` over `background`.
+fn flatten(image: &DynamicImage, background: Rgba<u8>) -> DynamicImage {
+    let mut image = image.to_rgba32f();
+    let [br, bg, bb, ba] = to_rgba32f(background).0;
+    for pixel in image.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let alpha = ba.mul_add(1.0 - a, a);
+        let blend = |fg: f32, bg: f32| {
+            if alpha == 0.0 {
+                0.0
+            } else {
+                fg.mul_add(a, bg * ba * (1.0 - a)) / alpha
+            }
         };
-        let mut buf = Vec::new();
-        favicon.write_to(&mut Cursor::new(&mut buf), format)?;
-        let frame = IcoFrame::with_encoded(buf, size, size, ColorType::Rgba8.into())?;
-        frames.push(frame);
+        *pixel = Rgba([blend(r, br), blend(g, bg), blend(b, bb), alpha]);
     }
-    let mut buf = Vec::new();
-    let encoder = IcoEncoder::new(&mut buf);
-    encoder.encode_images(&frames)?;
-    Ok(buf)
+    image.into()
+}
+
+/// Converts the 8-bit `color` to 32-bit floating point.
+fn to_rgba32f(color: Rgba<u8>) -> Rgba<f32> {
+    Rgba(color.0.map(|c| f32::from(c) / 255.0))
 }
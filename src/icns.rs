@@ -0,0 +1,50 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! An encoder for the Apple Icon Image format.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageResult, RgbaImage};
+
+// The magic number of an ICNS image:
+const ICNS_MAGIC: [u8; 4] = *b"icns";
+// The length of an ICNS header and an icon element header, in bytes:
+const ICNS_HEADER_SIZE: usize = 8;
+
+/// An icon element of an ICNS image.
+#[derive(Debug)]
+pub struct IcnsElement {
+    icon_type: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl IcnsElement {
+    /// Creates a new `IcnsElement` of `icon_type` by encoding `image` as a PNG
+    /// image.
+    pub fn png(icon_type: [u8; 4], image: &RgbaImage) -> ImageResult<Self> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)?;
+        Ok(Self { icon_type, data })
+    }
+}
+
+/// Encodes `elements` into an ICNS image.
+pub fn encode(elements: &[IcnsElement]) -> Vec<u8> {
+    let len = ICNS_HEADER_SIZE
+        + elements
+            .iter()
+            .map(|e| ICNS_HEADER_SIZE + e.data.len())
+            .sum::<usize>();
+    let mut buf = Vec::with_capacity(len);
+    buf.extend_from_slice(&ICNS_MAGIC);
+    buf.extend_from_slice(&u32::try_from(len).unwrap_or(u32::MAX).to_be_bytes());
+    for element in elements {
+        let len = ICNS_HEADER_SIZE + element.data.len();
+        buf.extend_from_slice(&element.icon_type);
+        buf.extend_from_slice(&u32::try_from(len).unwrap_or(u32::MAX).to_be_bytes());
+        buf.extend_from_slice(&element.data);
+    }
+    buf
+}
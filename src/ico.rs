@@ -0,0 +1,287 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! An encoder for the ICO file format.
+//!
+//! Unlike [`image::codecs::ico::IcoEncoder`], this stores BMP images as DIBs
+//! with an AND mask, and supports the paletted images used by legacy
+//! applications.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageResult, Rgba, RgbaImage};
+
+// Enum value indicating an ICO image (as opposed to a CUR image):
+const ICO_IMAGE_TYPE: u16 = 1;
+// The length of an ICO file ICONDIR structure, in bytes:
+const ICO_ICONDIR_SIZE: usize = 6;
+// The length of an ICO file DIRENTRY structure, in bytes:
+const ICO_DIRENTRY_SIZE: usize = 16;
+// The length of a BITMAPINFOHEADER structure, in bytes:
+const BITMAPINFOHEADER_SIZE: u32 = 40;
+// Pixels with an alpha value less than this are transparent in the AND mask:
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Bit depth of a BMP image stored in an ICO image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BitDepth {
+    /// 16 colors palette with a 1-bit transparency mask.
+    Four,
+
+    /// 256 colors palette with a 1-bit transparency mask.
+    Eight,
+
+    /// 32-bit RGBA.
+    ThirtyTwo,
+}
+
+impl BitDepth {
+    /// Returns the number of bits per pixel.
+    const fn bits(self) -> u16 {
+        match self {
+            Self::Four => 4,
+            Self::Eight => 8,
+            Self::ThirtyTwo => 32,
+        }
+    }
+
+    /// Returns the number of colors in the palette.
+    const fn palette_len(self) -> usize {
+        match self {
+            Self::Four => 16,
+            Self::Eight => 256,
+            Self::ThirtyTwo => 0,
+        }
+    }
+}
+
+/// An image entry of an ICO image.
+#[derive(Debug)]
+pub struct IcoFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    palette_len: usize,
+    bits: u16,
+}
+
+impl IcoFrame {
+    /// Creates a new `IcoFrame` by encoding `image` as a PNG image.
+    pub fn png(image: &RgbaImage) -> ImageResult<Self> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)?;
+        Ok(Self {
+            data,
+            width: image.width(),
+            height: image.height(),
+            palette_len: 0,
+            bits: 32,
+        })
+    }
+
+    /// Creates a new `IcoFrame` by encoding `image` as a BMP image with the
+    /// specified bit depth.
+    pub fn bmp(image: &RgbaImage, depth: BitDepth) -> Self {
+        let (width, height) = image.dimensions();
+        let palette = match depth {
+            BitDepth::ThirtyTwo => Vec::new(),
+            depth => make_palette(image, depth.palette_len()),
+        };
+
+        let mut data = Vec::new();
+        write_bitmap_info_header(&mut data, width, height, depth.bits());
+        for color in &palette {
+            data.extend_from_slice(&[color[2], color[1], color[0], 0]);
+        }
+
+        let xor_stride = row_stride(width, depth.bits());
+        for y in (0..height).rev() {
+            let mut row = vec![0; xor_stride];
+            for x in 0..width {
+                let pixel = image.get_pixel(x, y);
+                let x = x as usize;
+                match depth {
+                    BitDepth::ThirtyTwo => {
+                        row[x * 4..x * 4 + 4]
+                            .copy_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                    }
+                    BitDepth::Eight => row[x] = palette_index(&palette, *pixel),
+                    BitDepth::Four => {
+                        row[x / 2] |= palette_index(&palette, *pixel) << (4 * (1 - x % 2));
+                    }
+                }
+            }
+            data.extend_from_slice(&row);
+        }
+
+        let and_stride = row_stride(width, 1);
+        for y in (0..height).rev() {
+            let mut row = vec![0; and_stride];
+            for x in 0..width {
+                if image.get_pixel(x, y)[3] < ALPHA_THRESHOLD {
+                    let x = x as usize;
+                    row[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            data.extend_from_slice(&row);
+        }
+
+        Self {
+            data,
+            width,
+            height,
+            palette_len: palette.len(),
+            bits: depth.bits(),
+        }
+    }
+}
+
+/// Encodes `frames` into an ICO image.
+pub fn encode(frames: &[IcoFrame]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // Reserved field (must be zero):
+    buf.extend_from_slice(&0_u16.to_le_bytes());
+    // Image type (ICO or CUR):
+    buf.extend_from_slice(&ICO_IMAGE_TYPE.to_le_bytes());
+    // Number of images in the file:
+    buf.extend_from_slice(
+        &u16::try_from(frames.len())
+            .unwrap_or(u16::MAX)
+            .to_le_bytes(),
+    );
+
+    let mut offset = ICO_ICONDIR_SIZE + ICO_DIRENTRY_SIZE * frames.len();
+    for frame in frames {
+        // Image dimensions, stored as `0 => 256, n => n`:
+        buf.push(u8::try_from(frame.width).unwrap_or_default());
+        buf.push(u8::try_from(frame.height).unwrap_or_default());
+        // Number of colors in palette, stored as `0 => 256 or no palette`:
+        buf.push(u8::try_from(frame.palette_len).unwrap_or_default());
+        // Reserved field (must be zero):
+        buf.push(0);
+        // Color planes:
+        buf.extend_from_slice(&1_u16.to_le_bytes());
+        // Bits per pixel:
+        buf.extend_from_slice(&frame.bits.to_le_bytes());
+        // Image data size, in bytes:
+        buf.extend_from_slice(
+            &u32::try_from(frame.data.len())
+                .unwrap_or(u32::MAX)
+                .to_le_bytes(),
+        );
+        // Image data offset, in bytes:
+        buf.extend_from_slice(&u32::try_from(offset).unwrap_or(u32::MAX).to_le_bytes());
+        offset += frame.data.len();
+    }
+    for frame in frames {
+        buf.extend_from_slice(&frame.data);
+    }
+    buf
+}
+
+fn write_bitmap_info_header(buf: &mut Vec<u8>, width: u32, height: u32, bits: u16) {
+    let palette_len: u32 = match bits {
+        1..=8 => 1 << bits,
+        _ => 0,
+    };
+    let image_size =
+        u32::try_from((row_stride(width, bits) + row_stride(width, 1)) * height as usize)
+            .unwrap_or_default();
+
+    buf.extend_from_slice(&BITMAPINFOHEADER_SIZE.to_le_bytes());
+    buf.extend_from_slice(&width.to_le_bytes());
+    // The height is doubled because the image is followed by the AND mask:
+    buf.extend_from_slice(&(height * 2).to_le_bytes());
+    // Color planes:
+    buf.extend_from_slice(&1_u16.to_le_bytes());
+    buf.extend_from_slice(&bits.to_le_bytes());
+    // Compression method (BI_RGB):
+    buf.extend_from_slice(&0_u32.to_le_bytes());
+    buf.extend_from_slice(&image_size.to_le_bytes());
+    // Horizontal and vertical resolution:
+    buf.extend_from_slice(&0_i32.to_le_bytes());
+    buf.extend_from_slice(&0_i32.to_le_bytes());
+    // Number of colors in the palette and important colors:
+    buf.extend_from_slice(&palette_len.to_le_bytes());
+    buf.extend_from_slice(&0_u32.to_le_bytes());
+}
+
+/// Returns the length of a row of a DIB, in bytes.
+const fn row_stride(width: u32, bits: u16) -> usize {
+    (width as usize * bits as usize).div_ceil(32) * 4
+}
+
+/// Creates a palette for `image` using the median cut algorithm.
+///
+/// The first entry is always black, which is used for the transparent pixels
+/// so that they do not change the screen.
+fn make_palette(image: &RgbaImage, len: usize) -> Vec<[u8; 3]> {
+    let pixels = image
+        .pixels()
+        .filter(|p| p[3] >= ALPHA_THRESHOLD)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect::<Vec<_>>();
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < len - 1 {
+        let Some((i, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = (0..3)
+                    .map(|c| {
+                        let min = b.iter().map(|p| p[c]).min().unwrap_or_default();
+                        let max = b.iter().map(|p| p[c]).max().unwrap_or_default();
+                        (c, max - min)
+                    })
+                    .max_by_key(|(_, range)| *range)
+                    .unwrap_or_default();
+                (i, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|p| p[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let mut palette = Vec::with_capacity(len);
+    palette.push([0; 3]);
+    for colors in boxes.iter().filter(|b| !b.is_empty()) {
+        let mut sum = [0_usize; 3];
+        for p in colors {
+            for c in 0..3 {
+                sum[c] += usize::from(p[c]);
+            }
+        }
+        palette.push(sum.map(|s| u8::try_from(s / colors.len()).unwrap_or(u8::MAX)));
+    }
+    palette.resize(len, [0; 3]);
+    palette
+}
+
+/// Returns the index of the nearest color to `pixel` in `palette`.
+fn palette_index(palette: &[[u8; 3]], pixel: Rgba<u8>) -> u8 {
+    if pixel[3] < ALPHA_THRESHOLD {
+        return 0;
+    }
+    let distance = |color: &[u8; 3]| {
+        (0..3)
+            .map(|c| (i32::from(color[c]) - i32::from(pixel[c])).pow(2))
+            .sum::<i32>()
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .and_then(|(i, _)| u8::try_from(i).ok())
+        .unwrap_or_default()
+}
@@ -2,9 +2,17 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+mod apng;
 mod app;
 mod cli;
 mod generate;
+mod icns;
+mod ico;
+mod preprocess;
+#[cfg(feature = "psd")]
+mod psd;
+mod resample;
+mod trace;
 
 use std::{io, process::ExitCode};
 
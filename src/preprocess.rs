@@ -0,0 +1,194 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Preprocessing of the input image.
+
+use std::io::Cursor;
+
+use image::{
+    AnimationDecoder, DynamicImage, Frame, ImageFormat, ImageResult, Rgba, Rgba32FImage, RgbaImage,
+    codecs::png::PngDecoder, imageops,
+};
+use moxcms::{CmsError, ColorProfile, DataColorSpace, Layout, TransformOptions};
+
+use crate::resample;
+
+/// Frame of the animated image to use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameSelection {
+    /// The frame at the index.
+    Index(usize),
+
+    /// The frame with the highest contrast.
+    Best,
+}
+
+/// Tone mapping operator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ToneMap {
+    /// Reinhard operator.
+    Reinhard,
+
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+
+    /// Clipping to white.
+    Clip,
+}
+
+impl ToneMap {
+    /// Maps the value in linear light to the range between 0 and 1.
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            Self::Reinhard => value / (1.0 + value),
+            Self::Aces => {
+                (value * 2.51f32.mul_add(value, 0.03))
+                    / value.mul_add(2.43f32.mul_add(value, 0.59), 0.14)
+            }
+            Self::Clip => value,
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+/// Tone maps the HDR `image` in linear light with `operator`, and converts it
+/// to sRGB.
+///
+/// The colors of `image` are multiplied by 2 to the power of `exposure` before
+/// tone mapping.
+pub fn tone_map(image: &DynamicImage, operator: ToneMap, exposure: f32) -> DynamicImage {
+    let scale = exposure.exp2();
+    let mut image = image.to_rgba32f();
+    for pixel in image.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let [r, g, b] = [r, g, b].map(|c| resample::to_srgb(operator.apply(c.max(0.0) * scale)));
+        *pixel = Rgba([r, g, b, a.clamp(0.0, 1.0)]);
+    }
+    image.into()
+}
+
+/// Converts `image` from the color space of `icc_profile` to sRGB.
+///
+/// `image` is returned as is if the color space of `icc_profile` is neither RGB
+/// nor grayscale, because the decoders have already converted such images to
+/// RGB.
+pub fn to_srgb(image: &DynamicImage, icc_profile: &[u8]) -> Result<DynamicImage, CmsError> {
+    let profile = ColorProfile::new_from_slice(icc_profile)?;
+    let layout = match profile.color_space {
+        DataColorSpace::Rgb => Layout::Rgba,
+        DataColorSpace::Gray => Layout::GrayAlpha,
+        _ => return Ok(image.clone()),
+    };
+    let transform = profile.create_transform_f32(
+        layout,
+        &ColorProfile::new_srgb(),
+        Layout::Rgba,
+        TransformOptions::default(),
+    )?;
+    let mut image = image.to_rgba32f();
+    let src = if layout == Layout::GrayAlpha {
+        image.pixels().flat_map(|p| [p[0], p[3]]).collect()
+    } else {
+        image.to_vec()
+    };
+    transform.transform(&src, &mut image)?;
+    // The transform may return the values out of the gamut of sRGB:
+    for value in image.iter_mut() {
+        *value = value.clamp(0.0, 1.0);
+    }
+    Ok(image.into())
+}
+
+/// Decodes all the frames of the animated `input` in `format`.
+///
+/// Returns [`None`] if `format` does not support animation, or if `input` is
+/// not animated.
+pub fn frames(input: &[u8], format: ImageFormat) -> ImageResult<Option<Vec<Frame>>> {
+    let frames = match format {
+        #[cfg(feature = "gif")]
+        ImageFormat::Gif => {
+            let decoder = image::codecs::gif::GifDecoder::new(Cursor::new(input))?;
+            decoder.into_frames().collect_frames()?
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(input))?;
+            if !decoder.is_apng()? {
+                return Ok(None);
+            }
+            decoder.apng()?.into_frames().collect_frames()?
+        }
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => {
+            let decoder = image::codecs::webp::WebPDecoder::new(Cursor::new(input))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames().collect_frames()?
+        }
+        _ => return Ok(None),
+    };
+    Ok((frames.len() > 1).then_some(frames))
+}
+
+/// Returns the index of the frame of `frames` selected by `selection`.
+///
+/// Returns [`None`] if the index is out of range.
+pub fn select_frame(frames: &[Frame], selection: FrameSelection) -> Option<usize> {
+    match selection {
+        FrameSelection::Index(index) => (index < frames.len()).then_some(index),
+        FrameSelection::Best => frames
+            .iter()
+            .map(|frame| contrast(frame.buffer()))
+            .enumerate()
+            // The first one is preferred if the frames have the same contrast:
+            .max_by(|(i, a), (j, b)| a.total_cmp(b).then(j.cmp(i)))
+            .map(|(index, _)| index),
+    }
+}
+
+/// Returns the variance of the luminance of `image` with premultiplied alpha.
+fn contrast(image: &RgbaImage) -> f64 {
+    let luminances = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0.map(|c| f64::from(c) / 255.0);
+            0.0722f64.mul_add(b, 0.2126f64.mul_add(r, 0.7152 * g)) * a
+        })
+        .collect::<Vec<_>>();
+    #[allow(clippy::cast_precision_loss)]
+    let len = luminances.len().max(1) as f64;
+    let mean = luminances.iter().sum::<f64>() / len;
+    luminances.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / len
+}
+
+/// Crops `image` to the bounding box of the pixels whose alpha is greater than
+/// `tolerance`, and places it in the center of the transparent square canvas.
+///
+/// Returns [`None`] if there are no such pixels.
+pub fn trim(image: &DynamicImage, tolerance: u8) -> Option<DynamicImage> {
+    let alpha = image.to_rgba8();
+    let mut pixels = alpha
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel[3] > tolerance);
+    let (x, y, _) = pixels.next()?;
+    let (left, top, right, bottom) = pixels.fold((x, y, x, y), |(l, t, r, b), (x, y, _)| {
+        (l.min(x), t.min(y), r.max(x), b.max(y))
+    });
+    let cropped = image.crop_imm(left, top, right - left + 1, bottom - top + 1);
+    Some(square(&cropped.into_rgba32f()))
+}
+
+/// Places `image` in the center of the transparent square canvas.
+fn square(image: &Rgba32FImage) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let size = width.max(height);
+    let mut canvas = Rgba32FImage::new(size, size);
+    imageops::overlay(
+        &mut canvas,
+        image,
+        i64::from((size - width) / 2),
+        i64::from((size - height) / 2),
+    );
+    canvas.into()
+}
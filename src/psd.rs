@@ -0,0 +1,695 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A decoder for the Adobe Photoshop Document format.
+//!
+//! This supports the 8-bit and 16-bit RGB and grayscale images. The layers are
+//! composited with the normal blend mode, and their masks, effects and
+//! adjustments are not applied.
+
+use std::ops::Range;
+
+use image::{
+    ColorType, DynamicImage, ImageBuffer, ImageError, ImageResult, Limits, Pixel, Primitive, Rgba,
+    Rgba32FImage,
+    error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
+};
+
+// The magic number of a PSD image:
+const PSD_SIGNATURE: [u8; 4] = *b"8BPS";
+// The version of a PSD image (as opposed to a PSB image):
+const PSD_VERSION: u16 = 1;
+// Enum values indicating the color mode:
+const GRAYSCALE_MODE: u16 = 1;
+const RGB_MODE: u16 = 3;
+// Enum values indicating the compression method of the image data:
+const RAW_COMPRESSION: u16 = 0;
+const RLE_COMPRESSION: u16 = 1;
+// The ID of the image resource of the ICC profile:
+const ICC_PROFILE_ID: u16 = 1039;
+// The channel ID of the transparency mask of a layer:
+const ALPHA_CHANNEL_ID: i16 = -1;
+// The bit of the layer flags indicating that the layer is hidden:
+const HIDDEN_FLAG: u8 = 1 << 1;
+// Enum values of the section divider setting indicating an open or a closed
+// folder, and the end of a folder:
+const OPEN_FOLDER: u32 = 1;
+const CLOSED_FOLDER: u32 = 2;
+const FOLDER_END: u32 = 3;
+
+/// A PSD image whose image data are decoded on demand.
+#[derive(Debug)]
+pub struct Document<'a> {
+    width: u32,
+    height: u32,
+    channels: usize,
+    format: ColorFormat,
+    has_alpha: bool,
+    icc_profile: Option<&'a [u8]>,
+    image_data: &'a [u8],
+    layers: Vec<Node<'a>>,
+}
+
+impl<'a> Document<'a> {
+    /// Reads the header and the layer records of `data`.
+    pub fn new(data: &'a [u8]) -> ImageResult<Self> {
+        let mut reader = Reader::new(data);
+        if reader.bytes(4)? != PSD_SIGNATURE {
+            return Err(decoding_error("invalid signature"));
+        }
+        if reader.u16()? != PSD_VERSION {
+            return Err(unsupported_error("PSB images"));
+        }
+        reader.bytes(6)?;
+        let channels = usize::from(reader.u16()?);
+        let height = reader.u32()?;
+        let width = reader.u32()?;
+        let depth = match reader.u16()? {
+            depth @ (8 | 16) => Depth(depth),
+            depth => return Err(unsupported_error(&format!("{depth}-bit images"))),
+        };
+        let format = match reader.u16()? {
+            GRAYSCALE_MODE => ColorFormat { channels: 1, depth },
+            RGB_MODE => ColorFormat { channels: 3, depth },
+            mode => return Err(unsupported_error(&format!("color mode {mode}"))),
+        };
+        if channels < format.channels {
+            return Err(decoding_error("too few channels"));
+        }
+        // Skip the color mode data:
+        reader.section()?;
+        let icc_profile = read_icc_profile(&mut reader.section()?)?;
+        let (layers, has_alpha) = read_layers(&mut reader.section()?)?;
+        Ok(Self {
+            width,
+            height,
+            channels,
+            format,
+            has_alpha,
+            icc_profile,
+            image_data: reader.data,
+            layers: build_tree(layers)?,
+        })
+    }
+
+    /// Returns the embedded ICC profile.
+    pub const fn icc_profile(&self) -> Option<&'a [u8]> {
+        self.icc_profile
+    }
+
+    /// Decodes the composite image.
+    ///
+    /// The image is decoded with the bit depth of the document.
+    pub fn composite(&self) -> ImageResult<DynamicImage> {
+        match self.format.depth {
+            Depth(8) => self
+                .decode_composite(ColorType::Rgba8, |bytes| bytes[0])
+                .map(DynamicImage::from),
+            _ => self
+                .decode_composite(ColorType::Rgba16, |bytes| {
+                    u16::from_be_bytes([bytes[0], bytes[1]])
+                })
+                .map(DynamicImage::from),
+        }
+    }
+
+    /// Decodes the composite image into the buffer of `color_type` whose
+    /// channels are converted by `sample`.
+    fn decode_composite<T: Primitive>(
+        &self,
+        color_type: ColorType,
+        sample: impl Fn(&[u8]) -> T,
+    ) -> ImageResult<ImageBuffer<Rgba<T>, Vec<T>>>
+    where
+        Rgba<T>: Pixel<Subpixel = T>,
+    {
+        Limits::default().reserve_buffer(self.width, self.height, color_type)?;
+        let mut reader = Reader::new(self.image_data);
+        let compression = reader.u16()?;
+        // The channel following the color channels may be the transparency of
+        // the composite image:
+        let count = (self.format.channels + usize::from(self.has_alpha)).min(self.channels);
+        let mut image = ImageBuffer::from_pixel(
+            self.width,
+            self.height,
+            Rgba([
+                T::DEFAULT_MIN_VALUE,
+                T::DEFAULT_MIN_VALUE,
+                T::DEFAULT_MIN_VALUE,
+                T::DEFAULT_MAX_VALUE,
+            ]),
+        );
+        read_planes(
+            &mut reader,
+            compression,
+            count,
+            self.channels,
+            self.width,
+            self.height,
+            self.format.depth,
+            |plane, y, row| write_row(&mut image, plane, y, row, self.format, &sample),
+        )?;
+        Ok(image)
+    }
+
+    /// Decodes the image of the layer or the group at `path`.
+    ///
+    /// `path` is the names of the groups and the layer separated by "/". The
+    /// layer or the group is rendered even if it is hidden. Returns [`None`]
+    /// if there is no such layer or group.
+    pub fn layer(&self, path: &str) -> ImageResult<Option<DynamicImage>> {
+        let Some(node) = self.find(path) else {
+            return Ok(None);
+        };
+        let mut limits = Limits::default();
+        limits.reserve_buffer(self.width, self.height, ColorType::Rgba32F)?;
+        let mut canvas = Rgba32FImage::new(self.width, self.height);
+        node.render(&mut canvas, self.format, &mut limits)?;
+        Ok(Some(canvas.into()))
+    }
+
+    /// Returns the layer or the group at `path`.
+    fn find(&self, path: &str) -> Option<&Node<'a>> {
+        let mut nodes = self.layers.as_slice();
+        let mut found = None;
+        for name in path.split('/') {
+            // The topmost one is preferred if the names are duplicated:
+            let node = nodes.iter().rev().find(|node| node.layer().name == name)?;
+            nodes = match node {
+                Node::Layer(_) => &[],
+                Node::Group(_, children) => children,
+            };
+            found = Some(node);
+        }
+        found
+    }
+}
+
+/// A layer record and the image data of its channels.
+#[derive(Debug)]
+struct Layer<'a> {
+    name: String,
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    opacity: f32,
+    is_hidden: bool,
+    divider: Option<u32>,
+    channels: Vec<(i16, &'a [u8])>,
+}
+
+impl Layer<'_> {
+    /// Decodes the image of the layer.
+    ///
+    /// The memory for the image is reserved from `limits`.
+    fn image(&self, format: ColorFormat, limits: &mut Limits) -> ImageResult<Rgba32FImage> {
+        limits.reserve_buffer(self.width, self.height, ColorType::Rgba32F)?;
+        let mut image =
+            Rgba32FImage::from_pixel(self.width, self.height, Rgba([0.0, 0.0, 0.0, 1.0]));
+        for (id, data) in &self.channels {
+            // The user supplied layer masks are not applied:
+            let index = match *id {
+                ALPHA_CHANNEL_ID => format.channels,
+                id => match usize::try_from(id) {
+                    Ok(id) if id < format.channels => id,
+                    _ => continue,
+                },
+            };
+            let mut reader = Reader::new(data);
+            let compression = reader.u16()?;
+            read_planes(
+                &mut reader,
+                compression,
+                1,
+                1,
+                self.width,
+                self.height,
+                format.depth,
+                |_, y, row| {
+                    write_row(&mut image, index, y, row, format, |bytes| {
+                        format.depth.sample(bytes)
+                    });
+                },
+            )?;
+        }
+        Ok(image)
+    }
+}
+
+/// A layer or a group in the tree of the layers.
+#[derive(Debug)]
+enum Node<'a> {
+    Layer(Layer<'a>),
+    Group(Layer<'a>, Vec<Self>),
+}
+
+impl Node<'_> {
+    /// Returns the layer record of the layer or the group.
+    const fn layer(&self) -> &Layer<'_> {
+        match self {
+            Self::Layer(layer) | Self::Group(layer, _) => layer,
+        }
+    }
+
+    /// Composites the layer or the group over `canvas`.
+    ///
+    /// The children of the group are composited together before compositing
+    /// the group with its opacity. The memory for the intermediate images is
+    /// reserved from `limits` while they are alive.
+    fn render(
+        &self,
+        canvas: &mut Rgba32FImage,
+        format: ColorFormat,
+        limits: &mut Limits,
+    ) -> ImageResult<()> {
+        let image = match self {
+            Self::Layer(layer) => {
+                let image = layer.image(format, limits)?;
+                composite(canvas, &image, layer.left, layer.top, layer.opacity);
+                image
+            }
+            Self::Group(layer, children) => {
+                limits.reserve_buffer(canvas.width(), canvas.height(), ColorType::Rgba32F)?;
+                let mut group = Rgba32FImage::new(canvas.width(), canvas.height());
+                for child in children.iter().filter(|child| !child.layer().is_hidden) {
+                    child.render(&mut group, format, limits)?;
+                }
+                composite(canvas, &group, 0, 0, layer.opacity);
+                group
+            }
+        };
+        limits.free_usize(image.as_raw().len() * size_of::<f32>());
+        Ok(())
+    }
+}
+
+/// The number of the color channels and the bits per channel.
+#[derive(Clone, Copy, Debug)]
+struct ColorFormat {
+    channels: usize,
+    depth: Depth,
+}
+
+/// The number of bits per channel.
+#[derive(Clone, Copy, Debug)]
+struct Depth(u16);
+
+impl Depth {
+    /// Returns the number of bytes per channel.
+    const fn bytes(self) -> usize {
+        self.0 as usize / 8
+    }
+
+    /// Returns the value of the channel in `bytes`, between 0 and 1.
+    fn sample(self, bytes: &[u8]) -> f32 {
+        match (self.0, bytes) {
+            (8, [value]) => f32::from(*value) / f32::from(u8::MAX),
+            (16, [high, low]) => f32::from(u16::from_be_bytes([*high, *low])) / f32::from(u16::MAX),
+            _ => 0.0,
+        }
+    }
+}
+
+/// A reader of the big-endian binary data.
+#[derive(Debug)]
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> ImageResult<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(decoding_error("unexpected end of data"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> ImageResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> ImageResult<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> ImageResult<i16> {
+        let bytes = self.bytes(2)?;
+        Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> ImageResult<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> ImageResult<i32> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads the section whose length precedes it.
+    fn section(&mut self) -> ImageResult<Self> {
+        let len = self.u32()?;
+        let len = usize::try_from(len).map_err(|_| decoding_error("section is too long"))?;
+        self.bytes(len).map(Self::new)
+    }
+}
+
+/// Reads the ICC profile from the image resources section.
+fn read_icc_profile<'a>(reader: &mut Reader<'a>) -> ImageResult<Option<&'a [u8]>> {
+    while !reader.is_empty() {
+        if reader.bytes(4)? != b"8BIM" {
+            return Err(decoding_error("invalid image resource signature"));
+        }
+        let id = reader.u16()?;
+        // Skip the name, which is padded to an even length:
+        let len = usize::from(reader.u8()?);
+        reader.bytes(len + (len + 1) % 2)?;
+        let len = reader.u32()?;
+        let len = usize::try_from(len).map_err(|_| decoding_error("resource is too long"))?;
+        let data = reader.bytes(len)?;
+        // The data is also padded to an even length:
+        reader.bytes(len % 2)?;
+        if id == ICC_PROFILE_ID {
+            return Ok(Some(data));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the layer records and the image data of the layers from the layer and
+/// mask information section.
+///
+/// The layers are returned from bottom to top, with whether the channel
+/// following the color channels of the composite image is the transparency.
+/// It is the transparency only if the count of the layers is negative or the
+/// merged transparency block exists.
+fn read_layers<'a>(reader: &mut Reader<'a>) -> ImageResult<(Vec<Layer<'a>>, bool)> {
+    if reader.is_empty() {
+        return Ok((Vec::new(), false));
+    }
+    let mut info = reader.section()?;
+    // Skip the global layer mask information:
+    if !reader.is_empty() {
+        reader.section()?;
+    }
+    let mut has_merged_alpha = false;
+    while reader.data.len() >= 12 {
+        let signature = reader.bytes(4)?;
+        if signature != b"8BIM" && signature != b"8B64" {
+            break;
+        }
+        let key = reader.bytes(4)?;
+        let data = reader.section()?;
+        match key {
+            // The layers of the 16-bit image are stored in this block, and the
+            // layer information is empty:
+            b"Lr16" if info.is_empty() => info = data,
+            // Without this block, the channel following the color channels is
+            // usually a saved selection rather than the transparency:
+            b"Mtrn" | b"Mt16" | b"Mt32" => has_merged_alpha = true,
+            _ => {}
+        }
+    }
+    let mut reader = info;
+    if reader.is_empty() {
+        return Ok((Vec::new(), has_merged_alpha));
+    }
+    // The negative count also indicates that the first alpha channel of the
+    // image data is the transparency of the composite image:
+    let count = reader.i16()?;
+    let mut records = Vec::with_capacity(usize::from(count.unsigned_abs()));
+    for _ in 0..count.unsigned_abs() {
+        let (top, left, bottom, right) =
+            (reader.i32()?, reader.i32()?, reader.i32()?, reader.i32()?);
+        let channels = (0..reader.u16()?)
+            .map(|_| Ok((reader.i16()?, reader.u32()?)))
+            .collect::<ImageResult<Vec<_>>>()?;
+        if reader.bytes(4)? != b"8BIM" {
+            return Err(decoding_error("invalid blend mode signature"));
+        }
+        // Skip the blend mode key:
+        reader.bytes(4)?;
+        let opacity = f32::from(reader.u8()?) / f32::from(u8::MAX);
+        // Skip the clipping:
+        reader.u8()?;
+        let is_hidden = reader.u8()? & HIDDEN_FLAG != 0;
+        reader.u8()?;
+        let mut extra = reader.section()?;
+        // Skip the layer mask data and the blending ranges:
+        extra.section()?;
+        extra.section()?;
+        let len = extra.u8()?;
+        let mut name = extra
+            .bytes(usize::from(len))?
+            .iter()
+            .map(|&c| char::from(c))
+            .collect();
+        // The name is padded to a multiple of 4 bytes:
+        extra.bytes((4 - (usize::from(len) + 1) % 4) % 4)?;
+        let mut divider = None;
+        while extra.data.len() >= 12 {
+            let signature = extra.bytes(4)?;
+            if signature != b"8BIM" && signature != b"8B64" {
+                break;
+            }
+            let key = extra.bytes(4)?;
+            let mut data = extra.section()?;
+            match key {
+                b"luni" => {
+                    let len = data.u32()?;
+                    let units = (0..len)
+                        .map(|_| data.u16())
+                        .collect::<ImageResult<Vec<_>>>()?;
+                    name = String::from_utf16_lossy(&units);
+                }
+                b"lsct" | b"lsdk" => divider = Some(data.u32()?),
+                _ => {}
+            }
+        }
+        let layer = Layer {
+            name,
+            left,
+            top,
+            width: u32::try_from(right.saturating_sub(left)).unwrap_or_default(),
+            height: u32::try_from(bottom.saturating_sub(top)).unwrap_or_default(),
+            opacity,
+            is_hidden,
+            divider,
+            channels: Vec::new(),
+        };
+        records.push((layer, channels));
+    }
+    // The image data of the channels follow all the layer records:
+    let layers = records
+        .into_iter()
+        .map(|(layer, channels)| {
+            let channels = channels
+                .into_iter()
+                .map(|(id, len)| {
+                    let len =
+                        usize::try_from(len).map_err(|_| decoding_error("channel is too long"))?;
+                    Ok((id, reader.bytes(len)?))
+                })
+                .collect::<ImageResult<Vec<_>>>()?;
+            Ok(Layer { channels, ..layer })
+        })
+        .collect::<ImageResult<Vec<_>>>()?;
+    Ok((layers, count.is_negative() || has_merged_alpha))
+}
+
+/// Builds the tree of the layers from `layers` ordered from bottom to top.
+fn build_tree(layers: Vec<Layer<'_>>) -> ImageResult<Vec<Node<'_>>> {
+    let mut stack = vec![Vec::new()];
+    for layer in layers {
+        match layer.divider {
+            Some(FOLDER_END) => stack.push(Vec::new()),
+            Some(OPEN_FOLDER | CLOSED_FOLDER) => {
+                let children = stack
+                    .pop()
+                    .filter(|_| !stack.is_empty())
+                    .ok_or_else(|| decoding_error("unbalanced layer groups"))?;
+                if let Some(nodes) = stack.last_mut() {
+                    nodes.push(Node::Group(layer, children));
+                }
+            }
+            _ => {
+                if let Some(nodes) = stack.last_mut() {
+                    nodes.push(Node::Layer(layer));
+                }
+            }
+        }
+    }
+    match <[_; 1]>::try_from(stack) {
+        Ok([nodes]) => Ok(nodes),
+        Err(_) => Err(decoding_error("unbalanced layer groups")),
+    }
+}
+
+/// Reads the first `count` of the `total` planes of `width` and `height`.
+///
+/// Each row is passed to `f` with the index of its plane and its y coordinate.
+#[allow(clippy::too_many_arguments)]
+fn read_planes(
+    reader: &mut Reader<'_>,
+    compression: u16,
+    count: usize,
+    total: usize,
+    width: u32,
+    height: u32,
+    depth: Depth,
+    mut f: impl FnMut(usize, u32, &[u8]),
+) -> ImageResult<()> {
+    let row_len = width as usize * depth.bytes();
+    let rows = (0..count).flat_map(|plane| (0..height).map(move |y| (plane, y)));
+    match compression {
+        RAW_COMPRESSION => {
+            for (plane, y) in rows {
+                f(plane, y, reader.bytes(row_len)?);
+            }
+        }
+        RLE_COMPRESSION => {
+            // The byte counts of all the rows of all the planes precede the
+            // data:
+            let lens = (0..total * height as usize)
+                .map(|_| reader.u16())
+                .collect::<ImageResult<Vec<_>>>()?;
+            for ((plane, y), len) in rows.zip(lens) {
+                f(
+                    plane,
+                    y,
+                    &unpack_bits(reader.bytes(usize::from(len))?, row_len)?,
+                );
+            }
+        }
+        compression => {
+            return Err(unsupported_error(&format!(
+                "compression method {compression}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the row compressed with the run-length encoding of `PackBits`.
+fn unpack_bits(mut data: &[u8], len: usize) -> ImageResult<Vec<u8>> {
+    let mut row = Vec::with_capacity(len);
+    while let Some((&header, rest)) = data.split_first() {
+        let header = i8::from_be_bytes([header]);
+        data = rest;
+        match header {
+            0.. => {
+                let (literal, rest) = data
+                    .split_at_checked(header.unsigned_abs() as usize + 1)
+                    .ok_or_else(|| decoding_error("invalid run-length encoded data"))?;
+                row.extend_from_slice(literal);
+                data = rest;
+            }
+            -127..=-1 => {
+                let (&value, rest) = data
+                    .split_first()
+                    .ok_or_else(|| decoding_error("invalid run-length encoded data"))?;
+                row.resize(row.len() + header.unsigned_abs() as usize + 1, value);
+                data = rest;
+            }
+            i8::MIN => {}
+        }
+    }
+    if row.len() != len {
+        return Err(decoding_error("invalid run-length encoded data"));
+    }
+    Ok(row)
+}
+
+/// Writes the `row` at `y` of the `plane` to the channels of `image` which it
+/// is stored in. The values of the row are converted by `sample`.
+fn write_row<T: Primitive>(
+    image: &mut ImageBuffer<Rgba<T>, Vec<T>>,
+    plane: usize,
+    y: u32,
+    row: &[u8],
+    format: ColorFormat,
+    sample: impl Fn(&[u8]) -> T,
+) where
+    Rgba<T>: Pixel<Subpixel = T>,
+{
+    let width = image.width() as usize;
+    let start = y as usize * width * 4;
+    let Some(pixels) = image.get_mut(start..start + width * 4) else {
+        return;
+    };
+    let channels = channels(plane, format.channels);
+    for (pixel, bytes) in pixels
+        .chunks_exact_mut(4)
+        .zip(row.chunks_exact(format.depth.bytes()))
+    {
+        pixel[channels.clone()].fill(sample(bytes));
+    }
+}
+
+/// Returns the channels of the RGBA pixel which the `plane` is stored in.
+///
+/// The plane following the color planes is the transparency.
+const fn channels(plane: usize, color_channels: usize) -> Range<usize> {
+    match plane {
+        0 if color_channels == 1 => 0..3,
+        plane if plane == color_channels => 3..4,
+        plane => plane..plane + 1,
+    }
+}
+
+/// Composites `image` over `canvas` at (`left`, `top`) with `opacity`.
+fn composite(canvas: &mut Rgba32FImage, image: &Rgba32FImage, left: i32, top: i32, opacity: f32) {
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let (Some(cx), Some(cy)) = (
+            left.checked_add_unsigned(x)
+                .and_then(|x| u32::try_from(x).ok()),
+            top.checked_add_unsigned(y)
+                .and_then(|y| u32::try_from(y).ok()),
+        ) else {
+            continue;
+        };
+        let Some(dst) = canvas.get_pixel_mut_checked(cx, cy) else {
+            continue;
+        };
+        let [sr, sg, sb, sa] = pixel.0;
+        let [dr, dg, db, da] = dst.0;
+        let sa = sa * opacity;
+        let alpha = da.mul_add(1.0 - sa, sa);
+        let blend = |src: f32, dst: f32| {
+            if alpha > 0.0 {
+                src.mul_add(sa, dst * da * (1.0 - sa)) / alpha
+            } else {
+                0.0
+            }
+        };
+        *dst = Rgba([blend(sr, dr), blend(sg, dg), blend(sb, db), alpha]);
+    }
+}
+
+fn decoding_error(message: &str) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name(String::from("PSD")),
+        String::from(message),
+    ))
+}
+
+fn unsupported_error(feature: &str) -> ImageError {
+    ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+        ImageFormatHint::Name(String::from("PSD")),
+        UnsupportedErrorKind::GenericFeature(String::from(feature)),
+    ))
+}
@@ -0,0 +1,565 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Resampling of the images.
+
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
+
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage, imageops};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+
+/// Sampling filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Filter {
+    /// Nearest Neighbor.
+    Nearest,
+
+    /// Box filter, which averages the area covered by each pixel.
+    Box,
+
+    /// Linear Filter.
+    Triangle,
+
+    /// Cubic Filter.
+    CatmullRom,
+
+    /// Mitchell-Netravali Filter.
+    Mitchell,
+
+    /// Gaussian Filter.
+    Gaussian,
+
+    /// Lanczos with the specified number of lobes.
+    Lanczos(u8),
+}
+
+impl Filter {
+    /// Returns the radius of the kernel, in pixels.
+    fn support(self) -> f32 {
+        match self {
+            Self::Nearest | Self::Box => 0.5,
+            Self::Triangle => 1.0,
+            Self::CatmullRom | Self::Mitchell => 2.0,
+            Self::Gaussian => 3.0,
+            Self::Lanczos(lobes) => f32::from(lobes),
+        }
+    }
+
+    /// Returns the value of the kernel at `x`.
+    fn kernel(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Nearest | Self::Box if x < 0.5 => 1.0,
+            // The pixels on the boundary are covered by half:
+            Self::Nearest | Self::Box if x <= 0.5 => 0.5,
+            Self::Triangle if x < 1.0 => 1.0 - x,
+            Self::CatmullRom => bc_cubic_spline(x, 0.0, 0.5),
+            Self::Mitchell => bc_cubic_spline(x, 1.0 / 3.0, 1.0 / 3.0),
+            Self::Gaussian => {
+                const SIGMA: f32 = 0.5;
+                (-x.powi(2) / (2.0 * SIGMA.powi(2))).exp() / ((2.0 * PI).sqrt() * SIGMA)
+            }
+            Self::Lanczos(lobes) if x < f32::from(lobes) => sinc(x) * sinc(x / f32::from(lobes)),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Source image prepared for resampling.
+///
+/// The source image is stored with premultiplied alpha so that the colors of
+/// the transparent pixels do not bleed into the edges. If the resampling is in
+/// linear light, it is also converted from sRGB to linear light. The resized
+/// images are converted back.
+///
+/// The source image is stored in 16 bits per channel, or in 32-bit floating
+/// point if it is in linear light or the input image is in floating point.
+///
+/// The resized images are cached, so each size is resized only once even if it
+/// is requested from multiple threads. They are kept in 32-bit floating point,
+/// and are quantized to 8 bits per channel only when they are encoded.
+#[derive(Debug)]
+pub struct Resampler {
+    filter: Filter,
+    linear: bool,
+    levels: Levels,
+    cache: Mutex<HashMap<u32, Arc<OnceLock<Arc<DynamicImage>>>>>,
+}
+
+impl Resampler {
+    /// Creates a new `Resampler` for `image`.
+    ///
+    /// `image` is consumed so that it is not kept alongside its copy. If
+    /// `progressive` is `true`, the images halved repeatedly from `image` are
+    /// created in advance, and each image is resized from the smallest one
+    /// which is not smaller than it.
+    pub fn new(image: DynamicImage, filter: Filter, linear: bool, progressive: bool) -> Self {
+        Self {
+            filter,
+            linear,
+            levels: Levels::new(image, linear, progressive),
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Returns the width of the source image.
+    pub fn size(&self) -> u32 {
+        match self.levels {
+            Levels::Sixteen(ref levels) => levels[0].width,
+            Levels::Float(ref levels) => levels[0].width,
+        }
+    }
+
+    /// Resizes the source image to `size`.
+    ///
+    /// The resized image is shared with the cache.
+    pub fn resize(&self, size: u32) -> Arc<DynamicImage> {
+        let image = Arc::clone(
+            self.cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(size)
+                .or_default(),
+        );
+        Arc::clone(image.get_or_init(|| Arc::new(self.resize_uncached(size))))
+    }
+
+    fn resize_uncached(&self, size: u32) -> DynamicImage {
+        self.levels.resize(size, self.filter, self.linear)
+    }
+}
+
+/// Resizes `image` to `size` once, without caching it.
+///
+/// Unlike `Resampler`, the halved images are not created, and the copy of
+/// `image` is dropped when this returns.
+pub fn resize(image: DynamicImage, size: u32, filter: Filter, linear: bool) -> DynamicImage {
+    Levels::new(image, linear, false).resize(size, filter, linear)
+}
+
+/// The source image and the images halved from it.
+#[derive(Debug)]
+enum Levels {
+    Sixteen(Vec<Level<u16>>),
+    Float(Vec<Level<f32>>),
+}
+
+impl Levels {
+    fn new(image: DynamicImage, linear: bool, progressive: bool) -> Self {
+        let is_float = matches!(
+            image,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        if linear || is_float {
+            let image = image.into_rgba32f();
+            let (width, height) = image.dimensions();
+            Self::Float(Level::new(width, height, image.into_raw(), linear).pyramid(progressive))
+        } else {
+            let image = image.into_rgba16();
+            let (width, height) = image.dimensions();
+            Self::Sixteen(Level::new(width, height, image.into_raw(), linear).pyramid(progressive))
+        }
+    }
+
+    /// Resizes the source image to `size`, and undoes the premultiplication.
+    fn resize(&self, size: u32, filter: Filter, linear: bool) -> DynamicImage {
+        let mut data = match *self {
+            Self::Sixteen(ref levels) => resize_levels(levels, size, filter),
+            Self::Float(ref levels) => resize_levels(levels, size, filter),
+        };
+        data.par_chunks_mut(4).for_each(|pixel| {
+            let a = pixel[3];
+            for c in &mut pixel[..3] {
+                *c = if a > 0.0 {
+                    let value = (*c / a).clamp(0.0, 1.0);
+                    if linear { to_srgb(value) } else { value }
+                } else {
+                    0.0
+                };
+            }
+            pixel[3] = a.clamp(0.0, 1.0);
+        });
+        Rgba32FImage::from_raw(size, size, data)
+            .expect("buffer should have the size of the image")
+            .into()
+    }
+}
+
+/// The premultiplied RGBA pixels of the image.
+#[derive(Debug)]
+struct Level<T> {
+    width: u32,
+    height: u32,
+    data: Vec<T>,
+}
+
+impl<T: Channel> Level<T> {
+    /// Premultiplies the RGBA pixels in `data` by their alpha, after
+    /// converting them to linear light if `linear` is `true`.
+    fn new(width: u32, height: u32, mut data: Vec<T>, linear: bool) -> Self {
+        data.par_chunks_mut(4).for_each(|pixel| {
+            let a = pixel[3].to_f32();
+            for c in &mut pixel[..3] {
+                let value = c.to_f32();
+                *c = T::from_f32(if linear { to_linear(value) } else { value } * a);
+            }
+        });
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Returns this image followed by the images halved repeatedly from it if
+    /// `progressive` is `true`.
+    fn pyramid(self, progressive: bool) -> Vec<Self> {
+        let mut levels = vec![self];
+        while let Some(level) = levels
+            .last()
+            .filter(|level| progressive && level.width > 1 && level.height > 1)
+        {
+            let (width, height) = (level.width / 2, level.height / 2);
+            let data = resample(level, width, height, Filter::Box);
+            levels.push(Self {
+                width,
+                height,
+                data,
+            });
+        }
+        levels
+    }
+}
+
+/// Resizes the smallest image of `levels` which is not smaller than `size` to
+/// `size` with `filter`.
+fn resize_levels<T: Channel>(levels: &[Level<T>], size: u32, filter: Filter) -> Vec<f32> {
+    let level = levels
+        .iter()
+        .rev()
+        .find(|level| level.width >= size)
+        .unwrap_or(&levels[0]);
+    if level.width == size && level.height == size {
+        level.data.iter().map(|c| c.to_f32()).collect()
+    } else {
+        resample(level, size, size, filter)
+    }
+}
+
+/// Type of the channels of the source image.
+trait Channel: Copy + Send + Sync {
+    /// Converts the value to between 0 and 1.
+    fn to_f32(self) -> f32;
+
+    /// Converts the value between 0 and 1 to this type.
+    fn from_f32(value: f32) -> Self;
+}
+
+impl Channel for u16 {
+    fn to_f32(self) -> f32 {
+        f32::from(self) * (1.0 / f32::from(Self::MAX))
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from_f32(value: f32) -> Self {
+        (value.clamp(0.0, 1.0) * f32::from(Self::MAX)).round() as Self
+    }
+}
+
+impl Channel for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+/// Sharpens `image` with the unsharp mask.
+///
+/// The unsharp mask is applied with premultiplied alpha, so the edges are also
+/// sharpened without getting colored.
+pub fn sharpen(image: &DynamicImage, sigma: f32, amount: f32) -> DynamicImage {
+    let mut image = image.to_rgba32f();
+    for pixel in image.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        *pixel = Rgba([r * a, g * a, b * a, a]);
+    }
+    let blurred = imageops::blur(&image, sigma);
+    for (pixel, blurred) in image.pixels_mut().zip(blurred.pixels()) {
+        let [r, g, b, a] = pixel.0;
+        let [br, bg, bb, ba] = blurred.0;
+        let a = (a - ba).mul_add(amount, a).clamp(0.0, 1.0);
+        let [r, g, b] = [(r, br), (g, bg), (b, bb)].map(|(c, bc)| {
+            if a > 0.0 {
+                ((c - bc).mul_add(amount, c) / a).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        });
+        *pixel = Rgba([r, g, b, a]);
+    }
+    image.into()
+}
+
+/// Quantizes `image` to 8 bits per channel.
+///
+/// If `dither` is `true`, the ordered dithering with the 8x8 Bayer matrix is
+/// applied, so the smooth gradients do not get banded.
+pub fn quantize(image: &DynamicImage, dither: bool) -> RgbaImage {
+    let image = image.to_rgba32f();
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let threshold = if dither {
+            #[allow(clippy::cast_precision_loss)]
+            let index = bayer_index(x % 8, y % 8) as f32;
+            (index + 0.5) / 64.0 - 0.5
+        } else {
+            0.0
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let pixel = image
+            .get_pixel(x, y)
+            .0
+            .map(|c| c.mul_add(255.0, threshold).round().clamp(0.0, 255.0) as u8);
+        Rgba(pixel)
+    })
+}
+
+/// Returns the index of (`x`, `y`) in the 8x8 Bayer matrix.
+const fn bayer_index(x: u32, y: u32) -> u32 {
+    // The bits of x XOR y and y are interleaved in reverse order:
+    let xy = x ^ y;
+    ((xy & 1) << 5)
+        | ((y & 1) << 4)
+        | ((xy & 2) << 2)
+        | ((y & 2) << 1)
+        | ((xy & 4) >> 1)
+        | ((y & 4) >> 2)
+}
+
+/// Resizes `image` to `width` and `height` with `filter`.
+///
+/// The rows of the resized image are computed in parallel. Each row is
+/// filtered vertically from the rows of `image`, and then horizontally.
+// `mul_add` is not used because it is slow on the targets without FMA:
+#[allow(clippy::suboptimal_flops)]
+fn resample<S: Channel, D: Channel>(
+    image: &Level<S>,
+    width: u32,
+    height: u32,
+    filter: Filter,
+) -> Vec<D> {
+    let horizontal = weights(image.width, width, filter);
+    let vertical = weights(image.height, height, filter);
+    let src_stride = image.width as usize * 4;
+    let mut resized = vec![D::from_f32(0.0); width as usize * height as usize * 4];
+    resized
+        .par_chunks_mut(width as usize * 4)
+        .zip(vertical.par_iter())
+        .for_each(|(dst, (start, weights))| {
+            let mut column = vec![0.0; src_stride];
+            for (sy, weight) in (*start as usize..).zip(weights) {
+                let src = &image.data[sy * src_stride..(sy + 1) * src_stride];
+                for (c, s) in column.iter_mut().zip(src) {
+                    *c += s.to_f32() * weight;
+                }
+            }
+            for (pixel, (start, weights)) in dst.chunks_exact_mut(4).zip(&horizontal) {
+                let mut sum = [0.0; 4];
+                for (sx, weight) in (*start as usize..).zip(weights) {
+                    for (s, c) in sum.iter_mut().zip(&column[sx * 4..sx * 4 + 4]) {
+                        *s += c * weight;
+                    }
+                }
+                for (c, s) in pixel.iter_mut().zip(sum) {
+                    *c = D::from_f32(s);
+                }
+            }
+        });
+    resized
+}
+
+/// Returns the first source pixel and the weights of the source pixels for
+/// each destination pixel.
+fn weights(src_len: u32, dst_len: u32, filter: Filter) -> Vec<(u32, Vec<f32>)> {
+    #[allow(clippy::cast_precision_loss)]
+    let ratio = src_len as f32 / dst_len as f32;
+    // The kernel is stretched when downscaling so that it covers all the source
+    // pixels:
+    let scale = ratio.max(1.0);
+    let support = filter.support() * scale;
+    (0..dst_len)
+        .map(|i| {
+            #[allow(clippy::cast_precision_loss)]
+            let center = (i as f32 + 0.5) * ratio;
+            if filter == Filter::Nearest {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                return ((center as u32).min(src_len - 1), vec![1.0]);
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (start, end) = (
+                (center - support).floor().max(0.0) as u32,
+                ((center + support).ceil() as u32).min(src_len),
+            );
+            #[allow(clippy::cast_precision_loss)]
+            let mut weights = (start..end)
+                .map(|j| filter.kernel((j as f32 + 0.5 - center) / scale))
+                .collect::<Vec<_>>();
+            let sum = weights.iter().sum::<f32>();
+            if sum.abs() > f32::EPSILON {
+                for weight in &mut weights {
+                    *weight /= sum;
+                }
+            }
+            (start, weights)
+        })
+        .collect()
+}
+
+/// Returns the value of the cubic filter with the parameters `b` and `c` at
+/// `x`.
+#[allow(clippy::suboptimal_flops)]
+fn bc_cubic_spline(x: f32, b: f32, c: f32) -> f32 {
+    let k = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c)
+            .mul_add(x, -18.0 + 12.0 * b + 6.0 * c)
+            .mul_add(x.powi(2), 6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c)
+            .mul_add(x, 6.0 * b + 30.0 * c)
+            .mul_add(x, -12.0 * b - 48.0 * c)
+            .mul_add(x, 8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    k / 6.0
+}
+
+/// Returns the normalized sinc function at `x`.
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Converts the sRGB encoded value to linear light.
+fn to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts the value in linear light to sRGB encoded.
+pub fn to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        value.powf(1.0 / 2.4).mul_add(1.055, -0.055)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use image::RgbaImage;
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+    use super::*;
+
+    #[test]
+    fn resize_once() {
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            Rgba([0, 0, 0, u8::try_from(x * y % 256).unwrap()])
+        });
+        let resampler = Resampler::new(image.into(), Filter::CatmullRom, false, false);
+        let images = [16, 32, 16, 32, 16]
+            .into_par_iter()
+            .map(|size| resampler.resize(size))
+            .collect::<Vec<_>>();
+        assert_eq!(resampler.cache.lock().unwrap().len(), 2);
+        assert!(Arc::ptr_eq(&images[0], &images[2]));
+        assert!(Arc::ptr_eq(&images[1], &images[3]));
+        assert_eq!(*images[0], resampler.resize_uncached(16));
+    }
+
+    #[test]
+    fn source_image_size() {
+        let image = RgbaImage::new(64, 32);
+        let resampler = Resampler::new(image.clone().into(), Filter::CatmullRom, false, false);
+        // The 8-bit image is stored in 16 bits per channel.
+        assert!(
+            matches!(resampler.levels, Levels::Sixteen(ref levels) if levels[0].data.len() == 64 * 32 * 4)
+        );
+        let resampler = Resampler::new(image.clone().into(), Filter::CatmullRom, true, false);
+        assert!(matches!(resampler.levels, Levels::Float(_)));
+        let resampler = Resampler::new(
+            DynamicImage::from(image).into_rgba32f().into(),
+            Filter::CatmullRom,
+            false,
+            true,
+        );
+        assert!(matches!(resampler.levels, Levels::Float(ref levels) if levels.len() == 6));
+    }
+
+    #[test]
+    fn resize_without_resampler() {
+        let image = RgbaImage::from_fn(64, 64, |x, y| {
+            Rgba([0, 0, 0, u8::try_from(x * y % 256).unwrap()])
+        });
+        let resampler = Resampler::new(image.clone().into(), Filter::CatmullRom, true, false);
+        assert_eq!(
+            resize(image.into(), 16, Filter::CatmullRom, true),
+            *resampler.resize(16)
+        );
+    }
+
+    #[test]
+    fn resize_large_image() {
+        let image = RgbaImage::from_fn(2048, 2048, |x, y| {
+            Rgba([0, 0, 0, u8::try_from((x ^ y) & 0xff).unwrap()])
+        });
+        let resampler = Resampler::new(image.into(), Filter::CatmullRom, false, false);
+        let start = Instant::now();
+        for size in [16, 32, 48, 180, 192, 512] {
+            assert_eq!(resampler.resize(size).width(), size);
+        }
+        // This takes less than a second in the release build.
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn bayer_matrix() {
+        let mut indices = (0..8)
+            .flat_map(|y| (0..8).map(move |x| bayer_index(x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(indices[..4], [0, 32, 8, 40]);
+        assert_eq!(indices[8..12], [48, 16, 56, 24]);
+        indices.sort_unstable();
+        assert_eq!(indices, (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn quantize_8_bit_image() {
+        let image = RgbaImage::from_fn(16, 16, |x, y| {
+            Rgba([0, u8::MAX, u8::try_from(x * 16 + y).unwrap(), 128])
+        });
+        let dithered = quantize(&image.clone().into(), true);
+        assert_eq!(quantize(&image.clone().into(), false), image);
+        // The 8-bit colors are not changed by the dithering.
+        assert_eq!(dithered, image);
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: 2024 Shun Sakai
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tracing the silhouette of an image into an SVG path.
+
+use std::collections::BTreeMap;
+
+use image::RgbaImage;
+
+// Pixels with an alpha value of at least this are inside the silhouette:
+const ALPHA_THRESHOLD: u8 = 128;
+
+type Point = (u32, u32);
+
+/// Traces the alpha silhouette of `image` and returns the path data of it.
+///
+/// The outlines follow the pixel edges, so the path covers exactly the
+/// opaque pixels when it is rasterized at the same size. The outer outlines
+/// are clockwise and the holes are counterclockwise, so the path can be filled
+/// with both the `nonzero` and the `evenodd` fill rule.
+pub fn trace(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let is_inside = |x: i64, y: i64| {
+        u32::try_from(x)
+            .ok()
+            .zip(u32::try_from(y).ok())
+            .filter(|&(x, y)| x < width && y < height)
+            .is_some_and(|(x, y)| image.get_pixel(x, y)[3] >= ALPHA_THRESHOLD)
+    };
+
+    let mut edges = BTreeMap::<Point, Vec<Point>>::new();
+    for y in 0..height {
+        for x in 0..width {
+            let (px, py) = (i64::from(x), i64::from(y));
+            if !is_inside(px, py) {
+                continue;
+            }
+            if !is_inside(px, py - 1) {
+                edges.entry((x, y)).or_default().push((x + 1, y));
+            }
+            if !is_inside(px + 1, py) {
+                edges.entry((x + 1, y)).or_default().push((x + 1, y + 1));
+            }
+            if !is_inside(px, py + 1) {
+                edges.entry((x + 1, y + 1)).or_default().push((x, y + 1));
+            }
+            if !is_inside(px - 1, py) {
+                edges.entry((x, y + 1)).or_default().push((x, y));
+            }
+        }
+    }
+
+    let mut path = Vec::new();
+    while let Some((&start, _)) = edges.first_key_value() {
+        let mut outline = vec![start];
+        let mut current = start;
+        while let Some(next) = edges.get_mut(&current).and_then(Vec::pop) {
+            if edges.get(&current).is_some_and(Vec::is_empty) {
+                edges.remove(&current);
+            }
+            current = next;
+            if current == start {
+                break;
+            }
+            outline.push(current);
+        }
+        path.push(to_path_data(&outline));
+    }
+    path.join("")
+}
+
+/// Converts the closed outline into the path data, merging collinear edges.
+fn to_path_data(outline: &[Point]) -> String {
+    let len = outline.len();
+    let is_corner = |i: usize| {
+        let prev = outline[(i + len - 1) % len];
+        let next = outline[(i + 1) % len];
+        prev.0 != next.0 && prev.1 != next.1
+    };
+    let corners = (0..len)
+        .filter(|&i| is_corner(i))
+        .map(|i| outline[i])
+        .collect::<Vec<_>>();
+    let Some(&(x, y)) = corners.first() else {
+        return String::new();
+    };
+
+    let mut commands = vec![format!("M{x} {y}")];
+    for points in corners.windows(2) {
+        let (current, next) = (points[0], points[1]);
+        if next.0 == current.0 {
+            commands.push(format!("V{}", next.1));
+        } else {
+            commands.push(format!("H{}", next.0));
+        }
+    }
+    commands.push(String::from("Z"));
+    commands.concat()
+}
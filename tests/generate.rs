@@ -4,8 +4,13 @@
 
 mod utils;
 
-use std::{env, fs};
+use std::{
+    env,
+    fs::{self, File},
+    io::BufReader,
+};
 
+use image::{AnimationDecoder, codecs::png::PngDecoder};
 use predicates::prelude::predicate;
 
 #[test]
@@ -134,6 +139,1417 @@ fn generate_png_ico() {
     );
 }
 
+#[test]
+fn generate_ico_with_png_threshold() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--ico-sizes")
+        .arg("256,16,32,48,64")
+        .arg("--ico-png-threshold")
+        .arg("64")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let ico = fs::read(out_dir.join("favicon.ico")).unwrap();
+    assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 5);
+    let entries = ico[6..]
+        .chunks_exact(16)
+        .take(5)
+        .map(|e| {
+            let offset = u32::from_le_bytes([e[12], e[13], e[14], e[15]]) as usize;
+            let is_png = ico[offset..].starts_with(&[0x89, 0x50, 0x4e, 0x47]);
+            (e[0], is_png)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [(16, false), (32, false), (48, false), (64, true), (0, true)]
+    );
+    assert_eq!(
+        image::open(out_dir.join("favicon.ico")).unwrap().width(),
+        256
+    );
+}
+
+#[test]
+fn generate_ico_with_invalid_size() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--ico-sizes")
+        .arg("16,257")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '257' for '--ico-sizes <SIZE>'",
+        ));
+}
+
+#[test]
+fn generate_ico_with_png_and_png_threshold() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--png")
+        .arg("--ico-png-threshold")
+        .arg("64")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--png' cannot be used with '--ico-png-threshold <SIZE>'",
+        ));
+}
+
+#[test]
+fn generate_compat_ico() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--ico-compat")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let ico = fs::read(out_dir.join("favicon.ico")).unwrap();
+    assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 9);
+    let entries = ico[6..]
+        .chunks_exact(16)
+        .take(9)
+        .map(|e| (e[0], e[2], u16::from_le_bytes([e[6], e[7]])))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [
+            (16, 16, 4),
+            (16, 0, 8),
+            (16, 0, 32),
+            (32, 16, 4),
+            (32, 0, 8),
+            (32, 0, 32),
+            (48, 16, 4),
+            (48, 0, 8),
+            (48, 0, 32)
+        ]
+    );
+    assert_eq!(
+        image::open(out_dir.join("favicon.ico"))
+            .unwrap()
+            .into_rgba8(),
+        image::open("tests/data/input/input.png")
+            .unwrap()
+            .resize(48, 48, image::imageops::FilterType::Nearest)
+            .into_rgba8()
+    );
+}
+
+#[test]
+fn generate_app_ico() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("app-ico")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let ico = fs::read(out_dir.join("app.ico")).unwrap();
+    assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 8);
+    let entries = ico[6..]
+        .chunks_exact(16)
+        .take(8)
+        .map(|e| {
+            let offset = u32::from_le_bytes([e[12], e[13], e[14], e[15]]) as usize;
+            let is_png = ico[offset..].starts_with(&[0x89, 0x50, 0x4e, 0x47]);
+            (e[0], is_png)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [
+            (16, false),
+            (20, false),
+            (24, false),
+            (32, false),
+            (40, false),
+            (48, false),
+            (64, false),
+            (0, true)
+        ]
+    );
+}
+
+#[test]
+fn generate_hicolor_icon_theme() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("hicolor")
+        .arg("--icon-name")
+        .arg("org.example.App")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("org.example.App"));
+    for size in [16, 22, 24, 32, 48, 64, 128, 256, 512] {
+        let icon = image::open(out_dir.join(format!(
+            "icons/hicolor/{size}x{size}/apps/org.example.App.png"
+        )))
+        .unwrap();
+        assert_eq!(icon.width(), size);
+        assert_eq!(icon.height(), size);
+    }
+}
+
+#[test]
+fn generate_with_invalid_icon_name() {
+    let out_dir = tempfile::tempdir().unwrap();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.path())
+        .arg("--target")
+        .arg("hicolor")
+        .arg("--icon-name")
+        .arg("../../x")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '../../x' for '--icon-name <NAME>'",
+        ));
+}
+
+#[test]
+fn generate_microsoft_tiles() {
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--target")
+            .arg("microsoft")
+            .arg("--theme-color")
+            .arg("brown")
+            .arg("--filter")
+            .arg("nearest")
+            .arg("data/input/input.png")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("msapplication-config"));
+        for (width, height) in [(70, 70), (150, 150), (310, 150), (310, 310)] {
+            let tile = image::open(out_dir.join(format!("mstile-{width}x{height}.png"))).unwrap();
+            assert_eq!(tile.width(), width);
+            assert_eq!(tile.height(), height);
+        }
+        let wide_tile = image::open(out_dir.join("mstile-310x150.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(wide_tile.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(wide_tile.get_pixel(309, 149).0, [0; 4]);
+        let browserconfig = fs::read_to_string(out_dir.join("browserconfig.xml")).unwrap();
+        assert!(browserconfig.contains(r#"<wide310x150logo src="/mstile-310x150.png" />"#));
+        assert!(browserconfig.contains("<TileColor>#a52a2a</TileColor>"));
+    }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--target")
+            .arg("microsoft")
+            .arg("--theme-color")
+            .arg("brown")
+            .arg("--tile-color")
+            .arg("lightslategray")
+            .arg("--filter")
+            .arg("nearest")
+            .arg("data/input/input.png")
+            .assert()
+            .success();
+        assert!(
+            fs::read_to_string(out_dir.join("browserconfig.xml"))
+                .unwrap()
+                .contains("<TileColor>#778899</TileColor>")
+        );
+    }
+}
+
+#[test]
+fn generate_mask_icon() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("mask-icon")
+        .arg("--mask-icon-color")
+        .arg("brown")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("#a52a2a"));
+    let svg = fs::read(out_dir.join("safari-pinned-tab.svg")).unwrap();
+    let tree = resvg::usvg::Tree::from_data(&svg, &resvg::usvg::Options::default()).unwrap();
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(256, 256).unwrap();
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::default(),
+        &mut pixmap.as_mut(),
+    );
+    let input = image::open("tests/data/input/transparent.png")
+        .unwrap()
+        .into_rgba8();
+    for (pixel, rendered) in input.pixels().zip(pixmap.pixels()) {
+        let is_opaque = pixel[3] >= 128;
+        assert_eq!(rendered.alpha(), if is_opaque { 255 } else { 0 });
+    }
+}
+
+#[test]
+fn generate_apple_startup_images() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("apple-startup")
+        .arg("--background-color")
+        .arg("brown")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "apple-touch-startup-image-2796x1290.png",
+        ));
+    for (width, height) in [(1290, 2796), (2796, 1290), (2048, 2732), (2732, 2048)] {
+        let startup_image =
+            image::open(out_dir.join(format!("apple-touch-startup-image-{width}x{height}.png")))
+                .unwrap()
+                .into_rgba8();
+        assert_eq!(startup_image.dimensions(), (width, height));
+        assert_eq!(startup_image.get_pixel(0, 0).0, [0xa5, 0x2a, 0x2a, 0xff]);
+        assert_eq!(
+            startup_image.get_pixel(width / 2, height / 2).0,
+            [0xa5, 0x2a, 0x2a, 0xff]
+        );
+    }
+    assert_eq!(
+        fs::read_dir(out_dir)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("apple-touch-startup-image-")
+            })
+            .count(),
+        34
+    );
+}
+
+#[test]
+fn generate_apple_legacy_favicons() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("apple-legacy")
+        .arg("--background-color")
+        .arg("brown")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("apple-touch-icon-152x152.png"))
+        .stdout(predicate::str::contains("apple-touch-icon-precomposed"));
+    for size in [57, 60, 72, 76, 114, 120, 144, 152, 167, 180] {
+        let favicon = image::open(out_dir.join(format!("apple-touch-icon-{size}x{size}.png")))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(favicon.dimensions(), (size, size));
+        assert!(favicon.pixels().all(|p| p[3] == u8::MAX));
+        assert_eq!(favicon.get_pixel(0, 0).0, [0xa5, 0x2a, 0x2a, 0xff]);
+    }
+    assert_eq!(
+        image::open(out_dir.join("apple-touch-icon-precomposed.png"))
+            .unwrap()
+            .into_rgba8(),
+        image::open(out_dir.join("apple-touch-icon.png"))
+            .unwrap()
+            .into_rgba8()
+    );
+}
+
+#[test]
+fn generate_flattened_apple_favicon() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--background-color")
+        .arg("brown")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("apple-touch-icon.png"))
+        .unwrap()
+        .into_rgba8();
+    assert!(favicon.pixels().all(|p| p[3] == u8::MAX));
+    assert_eq!(favicon.get_pixel(0, 0).0, [0xa5, 0x2a, 0x2a, 0xff]);
+    assert_eq!(favicon.get_pixel(90, 55).0, [0xff, 0x00, 0x00, 0xff]);
+}
+
+#[test]
+fn generate_with_flatten() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    let flattened_dir = out_dir.join("flattened");
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--no-flatten")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("-o")
+        .arg(&flattened_dir)
+        .arg("--flatten")
+        .arg("android,favicon")
+        .arg("--background-color")
+        .arg("brown")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert!(favicon.pixels().any(|p| (1..u8::MAX).contains(&p[3])));
+    let flattened = image::open(flattened_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    for (pixel, flattened) in favicon.pixels().zip(flattened.pixels()) {
+        let alpha = f32::from(pixel[3]) / 255.0;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let blend = |fg: u8, bg: u8| {
+            f32::from(fg)
+                .mul_add(alpha, f32::from(bg) * (1.0 - alpha))
+                .round() as u8
+        };
+        let expected = [
+            blend(pixel[0], 0xa5),
+            blend(pixel[1], 0x2a),
+            blend(pixel[2], 0x2a),
+            u8::MAX,
+        ];
+        // The favicons are flattened before quantizing them, so they may differ
+        // by the rounding error.
+        for (actual, expected) in flattened.0.into_iter().zip(expected) {
+            assert!(actual.abs_diff(expected) <= 1);
+        }
+    }
+    assert!(
+        image::open(flattened_dir.join("favicon-16x16.png"))
+            .unwrap()
+            .into_rgba8()
+            .pixels()
+            .all(|p| p[3] == u8::MAX)
+    );
+    assert!(
+        image::open(flattened_dir.join("apple-touch-icon.png"))
+            .unwrap()
+            .into_rgba8()
+            .pixels()
+            .any(|p| p[3] == u8::MIN)
+    );
+    assert!(
+        image::open(out_dir.join("apple-touch-icon.png"))
+            .unwrap()
+            .into_rgba8()
+            .pixels()
+            .any(|p| p[3] == u8::MIN)
+    );
+}
+
+#[test]
+fn generate_with_flatten_and_no_flatten() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--flatten")
+        .arg("apple")
+        .arg("--no-flatten")
+        .arg("data/input/transparent.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "the argument '--flatten <ICON_SET>' cannot be used with '--no-flatten'",
+        ));
+}
+
+#[test]
+fn generate_with_padding() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--padding")
+        .arg("25%")
+        .arg("--favicon-padding")
+        .arg("0%")
+        .arg("--no-flatten")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(47, 47)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(48, 48)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(143, 143)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(144, 144)[3], u8::MIN);
+    let favicon = image::open(out_dir.join("apple-touch-icon.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(44, 44)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(45, 45)[3], u8::MAX);
+    assert_eq!(
+        image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgba8(),
+        image::open("tests/data/output/favicon-32x32.png")
+            .unwrap()
+            .into_rgba8()
+    );
+}
+
+#[test]
+fn generate_with_shape() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--shape")
+        .arg("circle")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(0, 0)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(191, 191)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(96, 0)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(96, 96)[3], u8::MAX);
+    assert!((1..u8::MAX).contains(&favicon.get_pixel(0, 90)[3]));
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(0, 0)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(16, 16)[3], u8::MAX);
+    let favicon = image::open(out_dir.join("apple-touch-icon.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(0, 0)[3], u8::MAX);
+}
+
+#[test]
+fn generate_with_rounded_shape() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--shape")
+        .arg("rounded:25%")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(0, 0)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(96, 0)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(0, 96)[3], u8::MAX);
+}
+
+#[test]
+fn generate_with_invalid_shape() {
+    let out_dir = tempfile::tempdir().unwrap();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.path())
+        .arg("--shape")
+        .arg("rounded:51%")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'rounded:51%' for '--shape <SHAPE>'",
+        ));
+}
+
+#[test]
+fn generate_with_linear() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("srgb"))
+        .arg("data/input/stripes.png")
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("linear"))
+        .arg("--linear")
+        .arg("data/input/stripes.png")
+        .assert()
+        .success();
+    let srgb = image::open(out_dir.join("srgb/favicon-16x16.png"))
+        .unwrap()
+        .into_rgba8();
+    let linear = image::open(out_dir.join("linear/favicon-16x16.png"))
+        .unwrap()
+        .into_rgba8();
+    // The average of black and white is 50% gray in sRGB, but is 73% gray in
+    // linear light.
+    assert!((120..=136).contains(&srgb.get_pixel(8, 8)[0]));
+    assert!((180..=196).contains(&linear.get_pixel(8, 8)[0]));
+    assert_eq!(linear.get_pixel(8, 8)[3], u8::MAX);
+}
+
+#[test]
+fn generate_without_fringes() {
+    for args in [&[][..], &["--filter", "lanczos3"], &["--linear"]] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .args(args)
+            .arg("data/input/transparent.png")
+            .assert()
+            .success();
+        for file in [
+            "android-chrome-192x192.png",
+            "favicon-16x16.png",
+            "favicon-32x32.png",
+        ] {
+            let favicon = image::open(out_dir.join(file)).unwrap().into_rgba8();
+            assert!(favicon.pixels().any(|p| p[3] == u8::MAX));
+            assert!(favicon.pixels().any(|p| (1..u8::MAX).contains(&p[3])));
+            // The transparent pixels of the input image are green, so the edges
+            // get a green fringe if the alpha is not premultiplied.
+            for pixel in favicon.pixels().filter(|p| p[3] != u8::MIN) {
+                assert_eq!(pixel.0[..3], [u8::MAX, u8::MIN, u8::MIN], "{file}");
+            }
+        }
+    }
+}
+
+#[test]
+fn generate_with_sharpen() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("plain"))
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("sharp"))
+        .arg("--sharpen")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    let softness = |image: &image::RgbaImage| {
+        image
+            .pixels()
+            .map(|p| u32::from(p[3].min(u8::MAX - p[3])))
+            .sum::<u32>()
+    };
+    for file in ["favicon-16x16.png", "favicon-32x32.png"] {
+        let plain = image::open(out_dir.join("plain").join(file))
+            .unwrap()
+            .into_rgba8();
+        let sharp = image::open(out_dir.join("sharp").join(file))
+            .unwrap()
+            .into_rgba8();
+        assert!(softness(&sharp) < softness(&plain));
+        for pixel in sharp.pixels().filter(|p| p[3] != u8::MIN) {
+            assert_eq!(pixel.0[..3], [u8::MAX, u8::MIN, u8::MIN]);
+        }
+    }
+    assert_eq!(
+        image::open(out_dir.join("sharp/android-chrome-192x192.png"))
+            .unwrap()
+            .into_rgba8(),
+        image::open(out_dir.join("plain/android-chrome-192x192.png"))
+            .unwrap()
+            .into_rgba8()
+    );
+}
+
+#[test]
+fn generate_with_trim() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--trim=16")
+        .arg("data/input/wide.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(
+        favicon.get_pixel(0, 96).0,
+        [u8::MAX, u8::MIN, u8::MIN, u8::MAX]
+    );
+    assert_eq!(favicon.get_pixel(191, 96)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(96, 56)[3], u8::MAX);
+    assert_eq!(favicon.get_pixel(96, 40)[3], u8::MIN);
+    assert_eq!(favicon.get_pixel(96, 151)[3], u8::MIN);
+}
+
+#[test]
+fn generate_with_trim_without_tolerance() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--trim")
+        .arg("data/input/wide.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("android-chrome-192x192.png"))
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(favicon.get_pixel(0, 96)[3], 8);
+    assert_eq!(favicon.get_pixel(96, 96)[3], u8::MAX);
+}
+
+#[test]
+fn generate_with_trim_and_transparent_image() {
+    let out_dir = tempfile::tempdir().unwrap();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.path())
+        .arg("--trim=255")
+        .arg("data/input/wide.png")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "image does not have any non-transparent pixels",
+        ));
+}
+
+#[test]
+fn generate_from_non_square_image() {
+    let out_dir = tempfile::tempdir().unwrap();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.path())
+        .arg("data/input/wide.png")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("image is not square"));
+}
+
+#[cfg(feature = "jpeg")]
+#[test]
+fn generate_with_orientation() {
+    for orientation in 1..=8 {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg(format!("data/input/orientation-{orientation}.jpg"))
+            .assert()
+            .success();
+        let favicon = image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgb8();
+        for (x, y, expected) in [
+            (8, 8, [u8::MAX, u8::MIN, u8::MIN]),
+            (24, 8, [u8::MIN, u8::MAX, u8::MIN]),
+            (8, 24, [u8::MIN, u8::MIN, u8::MAX]),
+            (24, 24, [u8::MAX, u8::MAX, u8::MAX]),
+        ] {
+            let pixel = favicon.get_pixel(x, y);
+            for (actual, expected) in pixel.0.into_iter().zip(expected) {
+                assert!(
+                    actual.abs_diff(expected) < 32,
+                    "orientation {orientation}: ({x}, {y}) is {pixel:?}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "jpeg")]
+#[test]
+fn generate_with_no_auto_orient() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--no-auto-orient")
+        .arg("data/input/orientation-6.jpg")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgb8();
+    // The top-right corner is rotated to the top-left corner in the stored
+    // image.
+    let pixel = favicon.get_pixel(8, 8);
+    assert!(pixel[0] < 32 && pixel[1] > 223 && pixel[2] < 32);
+}
+
+#[test]
+fn generate_from_image_with_icc_profile() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("data/input/display-p3.png")
+        .assert()
+        .success();
+    let file = out_dir.join("favicon-32x32.png");
+    let favicon = image::open(&file).unwrap().into_rgb8();
+    // (200, 100, 50) in Display P3 is (215, 93, 31) in sRGB.
+    for (actual, expected) in favicon.get_pixel(16, 16).0.into_iter().zip([215, 93, 31]) {
+        assert!(actual.abs_diff(expected) <= 1);
+    }
+    // The outputs do not have an embedded ICC profile.
+    assert!(
+        !fs::read(file)
+            .unwrap()
+            .windows(4)
+            .any(|chunk_type| chunk_type == b"iCCP")
+    );
+}
+
+#[test]
+fn generate_from_image_with_invalid_icc_profile() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("data/input/invalid-icc-profile.png")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: could not convert the image to sRGB",
+        ));
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgb8();
+    // The image is not converted.
+    for (actual, expected) in favicon.get_pixel(16, 16).0.into_iter().zip([200, 100, 50]) {
+        assert!(actual.abs_diff(expected) <= 1);
+    }
+}
+
+#[cfg(feature = "jpeg")]
+#[test]
+fn generate_from_jpeg_with_icc_profile() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("data/input/adobe-rgb.jpg")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgb8();
+    // (200, 100, 50) in Adobe RGB is (227, 100, 42) in sRGB.
+    for (actual, expected) in favicon.get_pixel(16, 16).0.into_iter().zip([227, 100, 42]) {
+        assert!(actual.abs_diff(expected) <= 4);
+    }
+}
+
+#[cfg(feature = "hdr")]
+#[test]
+fn generate_with_tone_map() {
+    // The left half of "bright.hdr" is 4.0 and the right half is 0.25.
+    for (operator, expected) in [
+        (None, [231, 124]),
+        (Some("reinhard"), [231, 124]),
+        (Some("aces"), [252, 165]),
+        (Some("clip"), [255, 137]),
+    ] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        let mut command = utils::command::command();
+        command.arg("-o").arg(out_dir);
+        if let Some(operator) = operator {
+            command.arg("--tone-map").arg(operator);
+        }
+        command.arg("data/input/bright.hdr").assert().success();
+        let favicon = image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgb8();
+        for ((x, y), expected) in [(4, 16), (28, 16)].into_iter().zip(expected) {
+            let pixel = favicon.get_pixel(x, y);
+            assert!(
+                pixel.0.into_iter().all(|c| c.abs_diff(expected) <= 1),
+                "{operator:?}: ({x}, {y}) is {pixel:?}"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "hdr")]
+#[test]
+fn generate_with_exposure() {
+    for (operator, exposure, expected) in [("clip", "-2", [255, 71]), ("reinhard", "2", [248, 188])]
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--tone-map")
+            .arg(operator)
+            .arg("--exposure")
+            .arg(exposure)
+            .arg("data/input/bright.hdr")
+            .assert()
+            .success();
+        let favicon = image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgb8();
+        for ((x, y), expected) in [(4, 16), (28, 16)].into_iter().zip(expected) {
+            let pixel = favicon.get_pixel(x, y);
+            assert!(
+                pixel.0.into_iter().all(|c| c.abs_diff(expected) <= 1),
+                "{exposure}: ({x}, {y}) is {pixel:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn generate_with_invalid_tone_map() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--tone-map")
+        .arg("a")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'a' for '--tone-map <OPERATOR>'",
+        ));
+}
+
+#[test]
+fn generate_with_dither() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("default"))
+        .arg("data/input/gradient.png")
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("dither"))
+        .arg("--dither")
+        .arg("data/input/gradient.png")
+        .assert()
+        .success();
+    // "gradient.png" is the 16-bit horizontal gradient from 40% to 42% gray.
+    let expected = |x: u32| (0.4 + 0.02 * (f64::from(x) + 0.5) / 512.0) * 255.0;
+    let favicon = image::open(out_dir.join("default/android-chrome-512x512.png"))
+        .unwrap()
+        .into_luma8();
+    for (x, _, pixel) in favicon
+        .enumerate_pixels()
+        .filter(|(x, ..)| (8..504).contains(x))
+    {
+        assert!((f64::from(pixel[0]) - expected(x)).abs() <= 0.51);
+    }
+    // The average of each 8 columns of the dithered image is closer to the
+    // original than 8 bits.
+    let favicon = image::open(out_dir.join("dither/android-chrome-512x512.png"))
+        .unwrap()
+        .into_luma8();
+    let favicon = &favicon;
+    for x in (8..504).step_by(8) {
+        let columns = x..x + 8;
+        let actual = columns
+            .clone()
+            .flat_map(|x| (0..512).map(move |y| f64::from(favicon.get_pixel(x, y)[0])))
+            .sum::<f64>()
+            / 4096.0;
+        let expected = columns.map(expected).sum::<f64>() / 8.0;
+        assert!((actual - expected).abs() < 0.1, "columns {x}..{}", x + 8);
+    }
+}
+
+#[cfg(feature = "gif")]
+#[test]
+fn generate_with_frame() {
+    // The frames of "animated.gif" are red, half red and half blue, and blue.
+    for (frame, expected) in [
+        (None, [[u8::MAX, 0, 0], [u8::MAX, 0, 0]]),
+        (Some("2"), [[0, 0, u8::MAX], [0, 0, u8::MAX]]),
+        (Some("best"), [[u8::MAX, 0, 0], [0, 0, u8::MAX]]),
+    ] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        let mut command = utils::command::command();
+        command.arg("-o").arg(out_dir);
+        if let Some(frame) = frame {
+            command.arg("--frame").arg(frame);
+        }
+        command.arg("data/input/animated.gif").assert().success();
+        let favicon = image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgb8();
+        assert_eq!(favicon.get_pixel(4, 16).0, expected[0], "{frame:?}");
+        assert_eq!(favicon.get_pixel(28, 16).0, expected[1], "{frame:?}");
+    }
+}
+
+#[cfg(feature = "gif")]
+#[test]
+fn generate_with_frame_out_of_range() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--frame")
+        .arg("3")
+        .arg("data/input/animated.gif")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("image has only 3 frames"));
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--frame")
+        .arg("1")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("image has only 1 frame"));
+}
+
+#[test]
+fn generate_with_invalid_frame() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--frame")
+        .arg("a")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'a' for '--frame <FRAME>'",
+        ));
+}
+
+#[test]
+fn generate_with_apng() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--apng")
+        .arg("data/input/animated.png")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("favicon-animated.png"));
+    let decoder = PngDecoder::new(BufReader::new(
+        File::open(out_dir.join("favicon-animated.png")).unwrap(),
+    ))
+    .unwrap();
+    assert!(decoder.is_apng().unwrap());
+    let frames = decoder
+        .apng()
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    assert_eq!(frames.len(), 3);
+    for (frame, expected) in frames.iter().zip([
+        [[u8::MAX, 0, 0, u8::MAX], [u8::MAX, 0, 0, u8::MAX]],
+        [[u8::MAX, 0, 0, u8::MAX], [0, 0, u8::MAX, u8::MAX]],
+        [[0, 0, u8::MAX, u8::MAX], [0, 0, u8::MAX, u8::MAX]],
+    ]) {
+        assert_eq!(frame.buffer().dimensions(), (32, 32));
+        assert_eq!(frame.buffer().get_pixel(4, 16).0, expected[0]);
+        assert_eq!(frame.buffer().get_pixel(28, 16).0, expected[1]);
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        assert_eq!(numer / denom, 100);
+    }
+}
+
+#[cfg(feature = "webp")]
+#[test]
+fn generate_with_apng_and_orientation() {
+    // "animated-rotated.webp" has the same frames as "animated.png", and has the
+    // Exif orientation which rotates it by 90 degrees clockwise.
+    for (no_auto_orient, expected) in [(false, [(16, 4), (16, 28)]), (true, [(4, 16), (28, 16)])] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        let mut command = utils::command::command();
+        command.arg("-o").arg(out_dir).arg("--apng");
+        if no_auto_orient {
+            command.arg("--no-auto-orient");
+        }
+        command
+            .arg("data/input/animated-rotated.webp")
+            .assert()
+            .success();
+        let decoder = PngDecoder::new(BufReader::new(
+            File::open(out_dir.join("favicon-animated.png")).unwrap(),
+        ))
+        .unwrap();
+        let frames = decoder
+            .apng()
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        // The second frame is red on the left half and blue on the right half.
+        let frame = frames[1].buffer();
+        let [(rx, ry), (bx, by)] = expected;
+        let red = frame.get_pixel(rx, ry);
+        assert!(red[0] > 223 && red[2] < 32);
+        let blue = frame.get_pixel(bx, by);
+        assert!(blue[0] < 32 && blue[2] > 223);
+    }
+}
+
+#[test]
+fn generate_with_apng_from_still_image() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--apng")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("image is not animated"));
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_with_layer() {
+    // "layered.psd" has the white "Background" layer, the "Logo" group which
+    // has the red "Mark" layer and the green "Text" layer, and the hidden
+    // yellow "Hidden" layer. "layered-16.psd" is the 16-bit version of it.
+    const TRANSPARENT: [u8; 4] = [0; 4];
+    const WHITE: [u8; 4] = [u8::MAX; 4];
+    const RED: [u8; 4] = [u8::MAX, 0, 0, u8::MAX];
+    const GREEN: [u8; 4] = [0, u8::MAX, 0, u8::MAX];
+    const YELLOW: [u8; 4] = [u8::MAX, u8::MAX, 0, u8::MAX];
+    for input in ["data/input/layered.psd", "data/input/layered-16.psd"] {
+        for (layer, expected) in [
+            (None, [WHITE, RED, GREEN]),
+            (Some("Logo/Mark"), [TRANSPARENT, RED, TRANSPARENT]),
+            (Some("Logo"), [TRANSPARENT, RED, GREEN]),
+            (Some("Hidden"), [YELLOW, YELLOW, YELLOW]),
+        ] {
+            let out_dir = tempfile::tempdir().unwrap();
+            let out_dir = out_dir.path();
+            let mut command = utils::command::command();
+            command.arg("-o").arg(out_dir);
+            if let Some(layer) = layer {
+                command.arg("--layer").arg(layer);
+            }
+            command.arg(input).assert().success();
+            let favicon = image::open(out_dir.join("favicon-32x32.png"))
+                .unwrap()
+                .into_rgba8();
+            for ((x, y), expected) in [(1, 1), (16, 16), (16, 28)].into_iter().zip(expected) {
+                assert_eq!(
+                    favicon.get_pixel(x, y).0,
+                    expected,
+                    "{input}: {layer:?}: ({x}, {y})"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_with_nonexistent_layer() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--layer")
+        .arg("Logo/Text/Mark")
+        .arg("data/input/layered.psd")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            r#"could not find layer "Logo/Text/Mark""#,
+        ));
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--layer")
+        .arg("Logo")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "'--layer' can only be used with a PSD image",
+        ));
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_from_psd_with_extra_channel() {
+    // Both images are red, and have the extra channel which is opaque only on
+    // the left half. Only "transparent.psd" has the merged transparency block.
+    for (input, expected) in [
+        ("data/input/selection.psd", u8::MAX),
+        ("data/input/transparent.psd", u8::MIN),
+    ] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg(input)
+            .assert()
+            .success();
+        let favicon = image::open(out_dir.join("favicon-32x32.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(favicon.get_pixel(4, 16).0, [u8::MAX, 0, 0, u8::MAX]);
+        assert_eq!(favicon.get_pixel(28, 16)[3], expected, "{input}");
+    }
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_from_huge_psd() {
+    // "huge.psd" is 100000x100000, and has the "Huge" layer of the same size.
+    for layer in [None, Some("Huge")] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        let mut command = utils::command::command();
+        command.arg("-o").arg(out_dir);
+        if let Some(layer) = layer {
+            command.arg("--layer").arg(layer);
+        }
+        command
+            .arg("data/input/huge.psd")
+            .assert()
+            .failure()
+            .code(71)
+            .stderr(predicate::str::contains("Memory limit exceeded"));
+    }
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_from_psd_with_icc_profile() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("data/input/display-p3.psd")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgb8();
+    // (200, 100, 50) in Display P3 is (215, 93, 31) in sRGB.
+    for (actual, expected) in favicon.get_pixel(16, 16).0.into_iter().zip([215, 93, 31]) {
+        assert!(actual.abs_diff(expected) <= 1);
+    }
+}
+
+#[cfg(feature = "psd")]
+#[test]
+fn generate_from_psd_with_frame() {
+    for args in [["--frame", "0"], ["--apng", "--"]] {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .args(args)
+            .arg("data/input/layered.psd")
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains(
+                "'--frame' and '--apng' cannot be used with a PSD image",
+            ));
+    }
+}
+
+#[test]
+fn generate_with_invalid_padding() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--apple-padding")
+        .arg("50%")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '50%' for '--apple-padding <PERCENT>'",
+        ));
+}
+
+#[test]
+fn generate_with_invalid_target() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--target")
+        .arg("a")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value 'a' for '--target <TARGET>'",
+        ));
+}
+
+#[test]
+fn generate_icns() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--icns")
+        .arg("--filter")
+        .arg("nearest")
+        .arg("data/input/input.png")
+        .assert()
+        .success();
+    let icns = fs::read(out_dir.join("icon.icns")).unwrap();
+    assert!(icns.starts_with(b"icns"));
+    assert_eq!(
+        u32::from_be_bytes(icns[4..8].try_into().unwrap()) as usize,
+        icns.len()
+    );
+    let mut elements = Vec::new();
+    let mut data = &icns[8..];
+    while !data.is_empty() {
+        let len = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+        let image = image::load_from_memory(&data[8..len]).unwrap();
+        elements.push((str::from_utf8(&data[..4]).unwrap(), image.width()));
+        data = &data[len..];
+    }
+    assert_eq!(
+        elements,
+        [
+            ("ic11", 32),
+            ("ic12", 64),
+            ("ic07", 128),
+            ("ic08", 256),
+            ("ic13", 256),
+            ("ic09", 512),
+            ("ic14", 512),
+            ("ic10", 1024)
+        ]
+    );
+}
+
 #[test]
 fn generate_with_filter() {
     {
@@ -148,6 +1564,18 @@ fn generate_with_filter() {
             .assert()
             .success();
     }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--filter")
+            .arg("box")
+            .arg("data/input/input.png")
+            .assert()
+            .success();
+    }
     {
         let out_dir = tempfile::tempdir().unwrap();
         let out_dir = out_dir.path();
@@ -172,6 +1600,18 @@ fn generate_with_filter() {
             .assert()
             .success();
     }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--filter")
+            .arg("mitchell")
+            .arg("data/input/input.png")
+            .assert()
+            .success();
+    }
     {
         let out_dir = tempfile::tempdir().unwrap();
         let out_dir = out_dir.path();
@@ -196,6 +1636,92 @@ fn generate_with_filter() {
             .assert()
             .success();
     }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("--filter")
+            .arg("lanczos")
+            .arg("data/input/input.png")
+            .assert()
+            .success();
+    }
+}
+
+#[test]
+fn generate_with_lanczos_lobes() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--filter")
+        .arg("lanczos")
+        .arg("--lanczos-lobes")
+        .arg("5")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    let favicon = image::open(out_dir.join("favicon-32x32.png"))
+        .unwrap()
+        .into_rgba8();
+    assert!(favicon.pixels().any(|p| p[3] == u8::MAX));
+}
+
+#[test]
+fn generate_with_invalid_lanczos_lobes() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir)
+        .arg("--lanczos-lobes")
+        .arg("9")
+        .arg("data/input/input.png")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid value '9' for '--lanczos-lobes <N>'",
+        ));
+}
+
+#[test]
+fn generate_with_progressive() {
+    let out_dir = tempfile::tempdir().unwrap();
+    let out_dir = out_dir.path();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("direct"))
+        .arg("--filter")
+        .arg("box")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    utils::command::command()
+        .arg("-o")
+        .arg(out_dir.join("progressive"))
+        .arg("--filter")
+        .arg("box")
+        .arg("--progressive")
+        .arg("data/input/transparent.png")
+        .assert()
+        .success();
+    // Halving repeatedly with the box filter is the same as averaging the area
+    // directly.
+    for file in ["favicon-16x16.png", "favicon-32x32.png"] {
+        let direct = image::open(out_dir.join("direct").join(file))
+            .unwrap()
+            .into_rgba8();
+        let progressive = image::open(out_dir.join("progressive").join(file))
+            .unwrap()
+            .into_rgba8();
+        for (d, p) in direct.pixels().zip(progressive.pixels()) {
+            assert!(d[3].abs_diff(p[3]) <= 1);
+        }
+    }
 }
 
 #[test]
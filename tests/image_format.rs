@@ -2043,6 +2043,181 @@ fn generate_from_pnm_with_wrong_format() {
         .stderr(predicate::str::contains("could not read the image"));
 }
 
+#[cfg(feature = "psd")]
+#[test]
+fn generate_from_psd() {
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("data/input/input.psd")
+            .assert()
+            .success();
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-192x192.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-192x192.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-512x512.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-512x512.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("apple-touch-icon.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/apple-touch-icon.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-16x16.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-16x16.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-32x32.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-32x32.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert!(out_dir.join("favicon.ico").exists());
+    }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .write_stdin(include_bytes!("data/input/input.psd"))
+            .assert()
+            .success();
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-192x192.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-192x192.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-512x512.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-512x512.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("apple-touch-icon.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/apple-touch-icon.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-16x16.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-16x16.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-32x32.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-32x32.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert!(out_dir.join("favicon.ico").exists());
+    }
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("-f")
+            .arg("psd")
+            .arg("data/input/input.psd")
+            .assert()
+            .success();
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-192x192.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-192x192.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("android-chrome-512x512.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/android-chrome-512x512.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("apple-touch-icon.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/apple-touch-icon.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-16x16.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-16x16.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert_eq!(
+            image::open(out_dir.join("favicon-32x32.png"))
+                .unwrap()
+                .into_rgba8(),
+            image::open("tests/data/output/favicon-32x32.png")
+                .unwrap()
+                .into_rgba8()
+        );
+        assert!(out_dir.join("favicon.ico").exists());
+    }
+
+    {
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_dir = out_dir.path();
+        utils::command::command()
+            .arg("-o")
+            .arg(out_dir)
+            .arg("-f")
+            .arg("psd")
+            .arg("data/output/site.webmanifest")
+            .assert()
+            .failure()
+            .code(65)
+            .stderr(predicate::str::contains("could not read the image"));
+    }
+}
+
 #[cfg(feature = "qoi")]
 #[test]
 fn generate_from_qoi() {